@@ -1,10 +1,19 @@
 //! The main module.
 //! implements App and all of its features
 
-use crossterm::event::{KeyCode, MouseButton, MouseEvent};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crossterm::event::{KeyCode, MouseEvent, MouseEventKind};
 use ratatui::layout::Rect;
 
-use crate::{engine::Engine, position::Position};
+use crate::{
+    database::{GameList, Indexing},
+    engine::Engine,
+    position::{Editor, GameTree, Position},
+};
 
 #[derive(Debug)]
 /// Contains all state information of the app
@@ -13,6 +22,21 @@ pub struct App {
     pub position: Position,
     /// The stockfish instance
     pub stockfish: Engine,
+    /// The setup screen, while it is open
+    pub editor: Option<Editor>,
+    /// The file the game is saved to
+    pub path: PathBuf,
+    /// Whether the user was asked if [`App::path`] should be overwritten,
+    /// so pressing `s` again does it
+    pub overwrite: bool,
+    /// A short message for the user, like where the game was saved. Any key hides it
+    pub message: Option<String>,
+    /// A PGN file being indexed, while the progress bar is shown
+    pub indexing: Option<Indexing>,
+    /// The games of the last PGN file that was indexed
+    pub games: Option<GameList>,
+    /// Whether the game list is shown instead of the board
+    pub browsing: bool,
 }
 
 impl Default for App {
@@ -23,6 +47,13 @@ impl Default for App {
             )
             .unwrap(),
             stockfish: Engine::new(),
+            editor: None,
+            path: PathBuf::from("game.pgn"),
+            overwrite: false,
+            message: None,
+            indexing: None,
+            games: None,
+            browsing: false,
         }
     }
 }
@@ -32,17 +63,221 @@ impl App {
     /// # Return values
     /// returns true if the app should exit
     pub fn handle_input(&mut self, code: KeyCode) -> bool {
-        self.position.handle_keyboard(code);
-        matches!(code, KeyCode::Esc | KeyCode::Char('q'))
+        self.message = None;
+        let overwrite = std::mem::take(&mut self.overwrite);
+        if let Some(indexing) = self.indexing.take() {
+            if code == KeyCode::Esc {
+                indexing.progress().cancel();
+                self.message = Some("Stopped indexing".to_string());
+            } else {
+                self.indexing = Some(indexing);
+            }
+            return false;
+        }
+        if self.browsing {
+            self.handle_list_input(code);
+            return false;
+        }
+        if let Some(editor) = &mut self.editor {
+            match code {
+                KeyCode::Enter => {
+                    // Only a valid position closes the setup screen
+                    if let Ok(position) = editor.position() {
+                        self.position = position;
+                        self.editor = None;
+                    }
+                }
+                KeyCode::Esc => self.editor = None,
+                _ => {
+                    editor.handle_keyboard(code);
+                }
+            }
+            return false;
+        }
+        if self.position.handle_keyboard(code) {
+            return false;
+        }
+        match code {
+            KeyCode::Char('e') => self.editor = Some(Editor::from(&self.position)),
+            KeyCode::Left => {
+                self.position.unmake_move();
+            }
+            KeyCode::Right => {
+                self.position.go_forward();
+            }
+            KeyCode::Home => self.position.go_to(GameTree::ROOT),
+            KeyCode::End => self.position.go_to_end(),
+            KeyCode::Down => {
+                self.position.enter_variation();
+            }
+            KeyCode::Up => {
+                self.position.leave_variation();
+            }
+            KeyCode::Char('p') => {
+                self.position.promote_to_mainline();
+            }
+            KeyCode::PageUp => {
+                self.position.move_variation(true);
+            }
+            KeyCode::PageDown => {
+                self.position.move_variation(false);
+            }
+            KeyCode::Delete => {
+                self.position.delete_variation();
+            }
+            KeyCode::Char('x') => self.position.delete_remaining_moves(),
+            KeyCode::Char('X') => self.position.delete_previous_moves(),
+            KeyCode::Char('u') => {
+                self.position.undo();
+            }
+            KeyCode::Char('r') => {
+                self.position.redo();
+            }
+            KeyCode::Char('s') => self.save_game(overwrite),
+            KeyCode::Char('l') => self.browsing = self.games.is_some(),
+            KeyCode::Char('i') => {
+                let inserting = self.position.inserting();
+                self.position.set_inserting(!inserting);
+            }
+            _ => return matches!(code, KeyCode::Esc | KeyCode::Char('q')),
+        }
+        false
+    }
+
+    /// Moves through the game list, opens the selected game with Enter
+    /// and goes back to the board with Esc
+    fn handle_list_input(&mut self, code: KeyCode) {
+        let Some(list) = &mut self.games else {
+            self.browsing = false;
+            return;
+        };
+        match code {
+            KeyCode::Enter => {
+                if let Some(number) = list.selected() {
+                    self.open_game(number);
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.browsing = false,
+            _ => {
+                list.handle_keyboard(code);
+            }
+        }
+    }
+
+    /// Starts indexing a PGN file. The game list is shown once it is done
+    pub fn open_database(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        match Indexing::start(&path) {
+            Ok(indexing) => self.indexing = Some(indexing),
+            Err(error) => {
+                self.message = Some(format!("Couldn't open {}: {error}", path.display()));
+            }
+        }
+    }
+
+    /// Tells the engine about the position on the board and picks up the index once
+    /// indexing is done. Should be called regularly
+    pub fn update(&mut self) {
+        if let Some(unsupported) = self.stockfish.set_position(&self.position) {
+            self.message = Some(unsupported);
+        }
+        if !self.indexing.as_ref().is_some_and(Indexing::is_finished) {
+            return;
+        }
+        let Some(indexing) = self.indexing.take() else {
+            return;
+        };
+        match indexing.finish() {
+            Ok(index) => {
+                self.games = Some(GameList::new(index));
+                self.browsing = true;
+            }
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+            Err(error) => self.message = Some(format!("Couldn't index the file: {error}")),
+        }
+    }
+
+    /// Shows a game from the game list on the board
+    pub fn open_game(&mut self, number: usize) {
+        let Some(list) = &self.games else {
+            return;
+        };
+        match list.index().read_game(number) {
+            Ok(game) => {
+                self.position = game.position;
+                self.browsing = false;
+                if let Some(error) = game.errors.first() {
+                    self.message = Some(format!(
+                        "Game {} has {} problems, the first at {error}",
+                        number + 1,
+                        game.errors.len()
+                    ));
+                }
+            }
+            Err(error) => {
+                self.message = Some(format!("Couldn't read game {}: {error}", number + 1));
+            }
+        }
+    }
+
+    /// Writes the game to [`App::path`] as PGN and tells the user how it went.
+    /// A file that exists is only replaced if `overwrite` is set, otherwise the user is
+    /// asked to save again. The file of the game list is never replaced
+    pub fn save_game(&mut self, overwrite: bool) {
+        let path = self.path.display();
+        if self.is_database(&self.path) {
+            self.message = Some(format!("Won't replace the games in {path}"));
+            return;
+        }
+        if !overwrite && self.path.exists() {
+            self.message = Some(format!("{path} exists, press s again to overwrite it"));
+            self.overwrite = true;
+            return;
+        }
+        self.message = Some(match std::fs::write(&self.path, self.position.to_pgn()) {
+            Ok(()) => format!("Saved the game to {path}"),
+            Err(error) => format!("Couldn't save the game to {path}: {error}"),
+        });
+    }
+
+    /// Whether a file is the one the game list was read from
+    fn is_database(&self, path: &Path) -> bool {
+        let Some(list) = &self.games else {
+            return false;
+        };
+        match (path.canonicalize(), list.index().path().canonicalize()) {
+            (Ok(path), Ok(database)) => path == database,
+            _ => false,
+        }
     }
 
     /// Handles mouseevents
     pub fn handle_mouse(&mut self, event: MouseEvent, frame: &Rect) -> bool {
-        #[allow(clippy::single_match)]
+        if self.indexing.is_some() {
+            return false;
+        }
+        if let Some(list) = self.games.as_mut().filter(|_| self.browsing) {
+            match event.kind {
+                MouseEventKind::ScrollUp => list.scroll(-1),
+                MouseEventKind::ScrollDown => list.scroll(1),
+                _ => (),
+            }
+            return false;
+        }
+        if let Some(editor) = &mut self.editor {
+            editor.handle_mouse(frame, event);
+            return false;
+        }
         match event.kind {
-            crossterm::event::MouseEventKind::Down(MouseButton::Left) => {
+            MouseEventKind::Down(_) | MouseEventKind::Up(_) => {
                 self.position.handle_mouse(frame, event);
             }
+            MouseEventKind::ScrollUp => {
+                self.position.unmake_move();
+            }
+            MouseEventKind::ScrollDown => {
+                self.position.go_forward();
+            }
             _ => (),
         }
         false
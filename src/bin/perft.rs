@@ -0,0 +1,57 @@
+//! Runs a perft suite without starting the ui.
+//!
+//! Usage: `perft [suite] [max depth]`. The suite defaults to `data/perft.epd`, which is built into the binary
+
+use std::{env, fs, process::ExitCode, time::Instant};
+
+use chess_tui::epd::parse_suite;
+
+/// The suite that is run when no other one is given
+const BUNDLED_SUITE: &str = include_str!("../../data/perft.epd");
+
+fn main() -> color_eyre::Result<ExitCode> {
+    let mut args = env::args().skip(1);
+    let suite = match args.next() {
+        Some(path) => parse_suite(&fs::read_to_string(path)?),
+        None => parse_suite(BUNDLED_SUITE),
+    };
+    let max_depth = args.next().map_or(Ok(usize::MAX), |x| x.parse())?;
+
+    let mut failed = 0;
+    for case in &suite {
+        let start = Instant::now();
+        let mismatches = match case.verify(max_depth) {
+            Ok(x) => x,
+            Err(error) => {
+                println!("FAIL {}: {error}", case.fen);
+                failed += 1;
+                continue;
+            }
+        };
+        if mismatches.is_empty() {
+            println!("ok   {} ({:.2?})", case.fen, start.elapsed());
+            continue;
+        }
+        failed += 1;
+        println!("FAIL {}", case.fen);
+        for mismatch in mismatches {
+            println!(
+                "  depth {}: expected {}, found {}",
+                mismatch.depth, mismatch.expected, mismatch.found
+            );
+            for (r#move, nodes) in mismatch.divide {
+                println!("    {move}: {nodes}");
+            }
+        }
+    }
+    println!(
+        "{} of {} positions passed",
+        suite.len() - failed,
+        suite.len()
+    );
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
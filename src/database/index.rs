@@ -0,0 +1,380 @@
+//! Finds the games in a PGN file without reading their moves
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    widgets::{Block, Borders, Gauge},
+    Frame,
+};
+
+use super::list::Column;
+use crate::position::{read_pgn, read_tag, PgnGame};
+
+/// Where a game is in the file and the tags shown in the game list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameInfo {
+    /// The byte offset of the game's first line
+    pub offset: u64,
+    /// How many bytes the game takes up, up to the next game
+    pub length: u64,
+    /// The `White` tag
+    pub white: String,
+    /// The `Black` tag
+    pub black: String,
+    /// The `Result` tag
+    pub result: String,
+    /// The `Date` tag
+    pub date: String,
+    /// The `Event` tag
+    pub event: String,
+}
+
+impl GameInfo {
+    /// The tag shown in a column of the game list
+    #[must_use]
+    pub fn get(&self, column: Column) -> &str {
+        match column {
+            Column::White => &self.white,
+            Column::Black => &self.black,
+            Column::Result => &self.result,
+            Column::Date => &self.date,
+            Column::Event => &self.event,
+        }
+    }
+
+    /// Keeps a tag pair if it is one of the columns
+    fn set(&mut self, name: &str, value: String) {
+        let field = match name {
+            "White" => &mut self.white,
+            "Black" => &mut self.black,
+            "Result" => &mut self.result,
+            "Date" => &mut self.date,
+            "Event" => &mut self.event,
+            _ => return,
+        };
+        *field = value;
+    }
+}
+
+/// How far indexing has got. It is shared with the thread doing the work,
+/// which stops when it is cancelled
+#[derive(Debug, Default)]
+pub struct Progress {
+    bytes: AtomicU64,
+    games: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    /// How many bytes of the file have been gone through
+    #[must_use]
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// How many games have been found so far
+    #[must_use]
+    pub fn games(&self) -> usize {
+        self.games.load(Ordering::Relaxed)
+    }
+
+    /// Asks indexing to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether indexing was asked to stop
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Where each game in a PGN file is
+#[derive(Debug, Clone)]
+pub struct Index {
+    path: PathBuf,
+    games: Vec<GameInfo>,
+}
+
+impl Index {
+    /// Goes through a PGN file line by line, so only one line is held in memory at a time
+    /// # Errors
+    /// Fails if the file can't be read, or with [`io::ErrorKind::Interrupted`]
+    /// if `progress` was cancelled
+    pub fn build(path: impl Into<PathBuf>, progress: &Progress) -> io::Result<Self> {
+        let path = path.into();
+        let games = scan(BufReader::new(File::open(&path)?), progress)?;
+        Ok(Self { path, games })
+    }
+
+    /// The file the games are in
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The games in the order they are in the file
+    #[must_use]
+    pub fn games(&self) -> &[GameInfo] {
+        &self.games
+    }
+
+    /// Reads a game from the file, see [`read_pgn`]
+    /// # Errors
+    /// Fails if there is no such game or the file can't be read
+    pub fn read_game(&self, number: usize) -> io::Result<PgnGame> {
+        let info = self.games.get(number).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no game {}", number + 1))
+        })?;
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(info.offset))?;
+        let mut bytes = Vec::new();
+        file.take(info.length).read_to_end(&mut bytes)?;
+        Ok(read_pgn(&String::from_utf8_lossy(&bytes)))
+    }
+}
+
+/// Finds the games in a PGN. A game starts with its first tag pair,
+/// or with its movetext if it has no tags
+fn scan(mut reader: impl BufRead, progress: &Progress) -> io::Result<Vec<GameInfo>> {
+    let mut games: Vec<GameInfo> = Vec::new();
+    let mut line = Vec::new();
+    let mut offset = 0;
+    // Whether the current game's movetext has started, so a tag pair starts the next game
+    let mut in_movetext = true;
+    // Whether a blank line followed the current game's tags, so a game without movetext
+    // ends at the next tag pair
+    let mut tags_ended = false;
+    // Comments in braces can go over several lines, and their lines can look like tags
+    let mut in_comment = false;
+    loop {
+        if progress.is_cancelled() {
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "indexing was cancelled",
+            ));
+        }
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        let text = String::from_utf8_lossy(&line);
+        let trimmed = text.trim_start();
+        if in_comment || trimmed.is_empty() || text.starts_with('%') {
+            // Nothing that can start a game
+            tags_ended |= !in_movetext && trimmed.is_empty();
+        } else if trimmed.starts_with('[') {
+            if in_movetext || tags_ended {
+                games.push(GameInfo {
+                    offset,
+                    ..GameInfo::default()
+                });
+                progress.games.store(games.len(), Ordering::Relaxed);
+                in_movetext = false;
+                tags_ended = false;
+            }
+            if let Some((name, value)) = read_tag(trimmed) {
+                if let Some(game) = games.last_mut() {
+                    game.set(&name, value);
+                }
+            }
+        } else if !in_movetext || games.is_empty() {
+            if games.is_empty() {
+                games.push(GameInfo {
+                    offset,
+                    ..GameInfo::default()
+                });
+                progress.games.store(games.len(), Ordering::Relaxed);
+            }
+            in_movetext = true;
+        }
+        if in_movetext {
+            for c in trimmed.chars() {
+                match c {
+                    '}' if in_comment => in_comment = false,
+                    '{' if !in_comment => in_comment = true,
+                    ';' if !in_comment => break,
+                    _ => (),
+                }
+            }
+        }
+        offset += read as u64;
+        progress.bytes.store(offset, Ordering::Relaxed);
+    }
+    // Each game goes up to the next one
+    let mut end = offset;
+    for game in games.iter_mut().rev() {
+        game.length = end - game.offset;
+        end = game.offset;
+    }
+    Ok(games)
+}
+
+/// Indexing a file on a thread of its own, so the screen can show how far it got
+#[derive(Debug)]
+pub struct Indexing {
+    path: PathBuf,
+    size: u64,
+    progress: Arc<Progress>,
+    handle: JoinHandle<io::Result<Index>>,
+}
+
+impl Indexing {
+    /// Starts indexing a PGN file
+    /// # Errors
+    /// Fails if the file doesn't exist or its size can't be read
+    pub fn start(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let size = path.metadata()?.len();
+        let progress = Arc::new(Progress::default());
+        let handle = {
+            let path = path.clone();
+            let progress = Arc::clone(&progress);
+            thread::spawn(move || Index::build(path, &progress))
+        };
+        Ok(Self {
+            path,
+            size,
+            progress,
+            handle,
+        })
+    }
+
+    /// How far indexing has got
+    #[must_use]
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// The part of the file that has been gone through, from 0 to 1
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn fraction(&self) -> f64 {
+        if self.size == 0 {
+            return 1.0;
+        }
+        (self.progress.bytes() as f64 / self.size as f64).min(1.0)
+    }
+
+    /// Whether indexing is done, so [`Indexing::finish`] won't block
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Waits for indexing to end and returns the index
+    /// # Errors
+    /// Fails like [`Index::build`]
+    pub fn finish(self) -> io::Result<Index> {
+        self.handle
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("indexing failed")))
+    }
+
+    /// Draws a progress bar
+    pub fn draw(&self, frame: &mut Frame, chunk: Rect) {
+        let [_, middle, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(chunk);
+        let title = format!("Indexing {} (Esc cancels)", self.path.display());
+        let label = format!(
+            "{} games, {:.0}%",
+            self.progress.games(),
+            self.fraction() * 100.0
+        );
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::new().borders(Borders::ALL).title(title))
+                .ratio(self.fraction())
+                .label(label),
+            middle,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::{scan, Index, Indexing, Progress};
+    use crate::database::testing::TempPgn;
+
+    const PGN: &str = "[Event \"First\"]\n[White \"A\"]\n[Black \"B\"]\n\n\
+        1. e4 e5 { a comment\n[that looks like a tag] } 2. Nf3 1-0\n\n\
+        % an escaped line\n[Event \"Second\"]\n[White \"C \\\"the king\\\"\"]\n\
+        [Result \"0-1\"]\n\n1. d4 ; [Event \"rest of the line\"]\nd5 0-1\n";
+
+    #[test]
+    fn indexing() {
+        let games = scan(PGN.as_bytes(), &Progress::default()).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].event, "First");
+        assert_eq!(games[0].black, "B");
+        assert_eq!(games[1].white, "C \"the king\"");
+        assert_eq!(games[1].result, "0-1");
+        let second = PGN.find("[Event \"Second").unwrap() as u64;
+        assert_eq!(games[0].offset, 0);
+        assert_eq!(games[0].length, second);
+        assert_eq!(games[1].offset + games[1].length, PGN.len() as u64);
+
+        // A blank line ends tags without movetext, so the next tags are another game
+        let games = scan(
+            &b"[White \"A\"]\n\n[White \"B\"]\n[Black \"C\"]\n\n"[..],
+            &Progress::default(),
+        )
+        .unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(
+            (games[0].white.as_str(), games[0].black.as_str()),
+            ("A", "")
+        );
+        assert_eq!(
+            (games[1].white.as_str(), games[1].black.as_str()),
+            ("B", "C")
+        );
+        assert_eq!(games[1].offset, 13);
+
+        // Movetext without tags is a game too
+        let games = scan(&b"1. e4 *\n"[..], &Progress::default()).unwrap();
+        assert_eq!(games.len(), 1);
+
+        let progress = Progress::default();
+        progress.cancel();
+        let error = scan(PGN.as_bytes(), &progress).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn reading_games() {
+        let file = TempPgn::new(PGN);
+        let indexing = Indexing::start(file.path()).unwrap();
+        let index = indexing.finish().unwrap();
+        assert_eq!(index.games().len(), 2);
+        let game = index.read_game(1).unwrap();
+        assert!(game.errors.is_empty());
+        assert_eq!(game.position.header("Event"), Some("Second"));
+        assert_eq!(game.position.tree().mainline().len(), 2);
+        assert!(index.read_game(2).is_err());
+        let progress = Progress::default();
+        assert_eq!(
+            Index::build(file.path(), &progress).unwrap().games(),
+            index.games()
+        );
+        assert_eq!(progress.bytes(), PGN.len() as u64);
+    }
+}
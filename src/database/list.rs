@@ -0,0 +1,243 @@
+//! The screen listing the games of an [`Index`]
+
+use std::cell::Cell;
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Row, Table, TableState},
+    Frame,
+};
+
+use super::Index;
+
+/// A column of the game list, which it can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// The white player
+    White,
+    /// The black player
+    Black,
+    /// The result of the game
+    Result,
+    /// When the game was played
+    Date,
+    /// The tournament or match
+    Event,
+}
+
+impl Column {
+    /// The columns in the order they are shown. The keys 1 to 5 sort by them
+    pub const ALL: [Self; 5] = [
+        Self::White,
+        Self::Black,
+        Self::Result,
+        Self::Date,
+        Self::Event,
+    ];
+
+    /// The name shown above the column
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::White => "White",
+            Self::Black => "Black",
+            Self::Result => "Result",
+            Self::Date => "Date",
+            Self::Event => "Event",
+        }
+    }
+
+    /// How wide the column is drawn
+    const fn width(self) -> Constraint {
+        match self {
+            Self::White | Self::Black => Constraint::Fill(2),
+            Self::Result => Constraint::Length(8),
+            Self::Date => Constraint::Length(11),
+            Self::Event => Constraint::Fill(3),
+        }
+    }
+}
+
+/// A scrollable list of the games in a file
+#[derive(Debug)]
+pub struct GameList {
+    index: Index,
+    /// The games in the order they are shown, as their numbers in the file
+    order: Vec<usize>,
+    /// The row of the selected game
+    selected: usize,
+    /// The column the games are sorted by and whether it is descending.
+    /// Without one the games are in the order of the file
+    sort: Option<(Column, bool)>,
+    /// The first row that was drawn, which only changes to keep the selection on the screen
+    scroll: Cell<usize>,
+    /// How many rows fit on the screen, for paging
+    height: Cell<usize>,
+}
+
+impl GameList {
+    /// Lists the games in the order of the file
+    #[must_use]
+    pub fn new(index: Index) -> Self {
+        let order = (0..index.games().len()).collect();
+        Self {
+            index,
+            order,
+            selected: 0,
+            sort: None,
+            scroll: Cell::new(0),
+            height: Cell::new(1),
+        }
+    }
+
+    /// The games being listed
+    #[must_use]
+    pub const fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// The number in the file of the selected game, if there are any games
+    #[must_use]
+    pub fn selected(&self) -> Option<usize> {
+        self.order.get(self.selected).copied()
+    }
+
+    /// The games in the order they are shown
+    #[must_use]
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Sorts the games by a column, or goes back to the order of the file.
+    /// The same game stays selected
+    pub fn sort_by(&mut self, sort: Option<(Column, bool)>) {
+        let selected = self.selected();
+        let games = self.index.games();
+        self.order.sort_unstable();
+        if let Some((column, descending)) = sort {
+            // Sorting is stable, so equal games stay in the order of the file
+            self.order.sort_by(|&a, &b| {
+                let ordering = games[a].get(column).cmp(games[b].get(column));
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+        self.sort = sort;
+        self.selected = selected
+            .and_then(|game| self.order.iter().position(|&x| x == game))
+            .unwrap_or(0);
+    }
+
+    /// Moves the selection by some rows, stopping at the first and last game
+    pub fn scroll(&mut self, rows: isize) {
+        let last = self.order.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(rows).min(last);
+    }
+
+    /// Moves the selection with the arrow keys, pages and Home and End.
+    /// The keys 1 to 5 sort by a column, again to reverse it, and 0 goes back to the
+    /// order of the file.
+    /// Returns false if the key does nothing here
+    pub fn handle_keyboard(&mut self, code: KeyCode) -> bool {
+        let page = isize::try_from(self.height.get()).unwrap_or(1);
+        match code {
+            KeyCode::Up => self.scroll(-1),
+            KeyCode::Down => self.scroll(1),
+            KeyCode::PageUp => self.scroll(-page),
+            KeyCode::PageDown => self.scroll(page),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.scroll(isize::MAX),
+            KeyCode::Char('0') => self.sort_by(None),
+            KeyCode::Char(c @ '1'..='5') => {
+                let column = Column::ALL[c as usize - '1' as usize];
+                let descending = self.sort == Some((column, false));
+                self.sort_by(Some((column, descending)));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Draws the rows that fit on the screen
+    pub fn draw(&self, frame: &mut Frame, chunk: Rect) {
+        // The borders and the header take up three rows
+        let height = usize::from(chunk.height.saturating_sub(3)).max(1);
+        self.height.set(height);
+        let mut scroll = self.scroll.get().min(self.selected);
+        if self.selected >= scroll + height {
+            scroll = self.selected + 1 - height;
+        }
+        self.scroll.set(scroll);
+
+        let games = self.index.games();
+        let rows = self.order.iter().skip(scroll).take(height).map(|&number| {
+            let game = &games[number];
+            let mut cells = vec![(number + 1).to_string()];
+            cells.extend(Column::ALL.map(|column| game.get(column).to_string()));
+            Row::new(cells)
+        });
+        let mut header = vec!["#".to_string()];
+        header.extend(Column::ALL.iter().enumerate().map(|(i, &column)| {
+            let arrow = match self.sort {
+                Some((sorted, false)) if sorted == column => " ▲",
+                Some((sorted, true)) if sorted == column => " ▼",
+                _ => "",
+            };
+            format!("{} {}{arrow}", i + 1, column.name())
+        }));
+        let widths = std::iter::once(Constraint::Length(7)).chain(Column::ALL.map(Column::width));
+        let title = format!(
+            "{} games in {} (Enter opens, 1-5 sort, Esc goes back)",
+            games.len(),
+            self.index.path().display()
+        );
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).style(Style::new().add_modifier(Modifier::BOLD)))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .block(Block::new().borders(Borders::ALL).title(title));
+        let mut state = TableState::new().with_selected(Some(self.selected - scroll));
+        frame.render_stateful_widget(table, chunk, &mut state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::KeyCode;
+
+    use super::{Column, GameList};
+    use crate::database::{testing::TempPgn, Index, Progress};
+
+    #[test]
+    fn sorting() {
+        let pgn = "[White \"Tal\"]\n[Date \"1960.03.15\"]\n1. e4 *\n\
+            [White \"Botvinnik\"]\n[Date \"1961.03.15\"]\n1. d4 *\n\
+            [White \"Smyslov\"]\n[Date \"1957.03.05\"]\n1. c4 *\n";
+        let file = TempPgn::new(pgn);
+        let index = Index::build(file.path(), &Progress::default()).unwrap();
+
+        let mut list = GameList::new(index);
+        assert_eq!(list.order(), [0, 1, 2]);
+        list.handle_keyboard(KeyCode::Down);
+        assert_eq!(list.selected(), Some(1));
+        list.handle_keyboard(KeyCode::Char('1'));
+        assert_eq!(list.order(), [1, 2, 0]);
+        // The same game stays selected
+        assert_eq!(list.selected(), Some(1));
+        list.handle_keyboard(KeyCode::Char('1'));
+        assert_eq!(list.order(), [0, 2, 1]);
+        list.sort_by(Some((Column::Date, false)));
+        assert_eq!(list.order(), [2, 0, 1]);
+        list.handle_keyboard(KeyCode::End);
+        assert_eq!(list.selected(), Some(1));
+        list.handle_keyboard(KeyCode::PageDown);
+        assert_eq!(list.selected(), Some(1));
+        list.handle_keyboard(KeyCode::Char('0'));
+        assert_eq!(list.order(), [0, 1, 2]);
+        assert!(!list.handle_keyboard(KeyCode::Enter));
+    }
+}
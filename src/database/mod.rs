@@ -0,0 +1,10 @@
+//! Browsing files with many games. The file is only gone through once to find where
+//! each game starts and what its tags are, and a game is read when it is opened
+
+mod index;
+mod list;
+#[cfg(test)]
+mod testing;
+
+pub use index::{GameInfo, Index, Indexing, Progress};
+pub use list::{Column, GameList};
@@ -0,0 +1,34 @@
+//! Files for the tests of the database modules
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A PGN file in the temporary directory that is removed again when dropped,
+/// even if the test panics
+pub struct TempPgn(PathBuf);
+
+impl TempPgn {
+    /// Writes `pgn` to a file no other test is using
+    pub fn new(pgn: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let number = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("chess-tui-{}-{number}.pgn", process::id()));
+        fs::write(&path, pgn).unwrap();
+        Self(path)
+    }
+
+    /// Where the file is
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPgn {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
@@ -1,6 +1,8 @@
 #[derive(Debug, Copy, Clone)]
 pub struct Eval {
+    #[allow(unused)]
     length: usize,
+    #[allow(unused)]
     evals: [f64; 3],
 }
 
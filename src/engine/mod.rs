@@ -13,6 +13,8 @@ mod thread_stuff;
 use eval::Eval;
 use thread_stuff::Wait;
 
+use crate::position::{Position, Variant};
+
 use crossbeam::atomic::AtomicCell;
 use futures::{
     channel::{
@@ -32,8 +34,71 @@ pub struct Engine {
     #[allow(unused)]
     receiver: UnboundedReceiver<String>,
     eval: Arc<Wait<AtomicCell<Eval>>>,
-    #[allow(unused)]
     options: Arc<Mutex<Vec<self::options::Option>>>,
+    /// What the engine knows about the position
+    sent: Sent,
+}
+
+/// What the engine was last told about the position, so only changes are sent
+#[derive(Debug, Default)]
+struct Sent {
+    /// Whether `UCI_Chess960` was switched on
+    chess960: bool,
+    /// The last value `UCI_Variant` was set to
+    variant: Variant,
+    /// The last position
+    fen: Option<String>,
+    /// Why the engine can't analyze the last position it was given
+    unsupported: Option<String>,
+}
+
+impl Sent {
+    /// The actions that bring the engine up to date with a position.
+    /// `UCI_Variant` and `UCI_Chess960` are only set if `has_option` says the engine has them,
+    /// and the position is sent again after an option changed.
+    /// A variant or Chess960 game the engine can't be switched to isn't sent at all,
+    /// and the engine stops
+    fn update(&mut self, position: &Position, has_option: impl Fn(&str) -> bool) -> Vec<Action> {
+        let mut actions = Vec::new();
+        let variant = position.variant();
+        let unsupported = if variant != Variant::Standard && !has_option("UCI_Variant") {
+            Some(variant.to_string())
+        } else if position.is_chess960() && !has_option("UCI_Chess960") {
+            Some("Chess960".to_string())
+        } else {
+            None
+        };
+        if let Some(unsupported) = unsupported {
+            if self.unsupported.is_none() {
+                actions.push(Action::Stop);
+            }
+            self.unsupported = Some(format!("The engine doesn't support {unsupported}"));
+            self.fen = None;
+            return actions;
+        }
+        self.unsupported = None;
+        if variant != self.variant && has_option("UCI_Variant") {
+            self.variant = variant;
+            actions.push(Action::SetOption(
+                "UCI_Variant".to_string(),
+                variant.uci_name().to_string(),
+            ));
+        }
+        let chess960 = position.is_chess960();
+        if chess960 != self.chess960 && has_option("UCI_Chess960") {
+            self.chess960 = chess960;
+            actions.push(Action::SetOption(
+                "UCI_Chess960".to_string(),
+                chess960.to_string(),
+            ));
+        }
+        let fen = position.to_fen();
+        if !actions.is_empty() || self.fen.as_ref() != Some(&fen) {
+            self.fen = Some(fen.clone());
+            actions.push(Action::SetFen(fen));
+        }
+        actions
+    }
 }
 
 impl Engine {
@@ -85,6 +150,11 @@ impl Engine {
                             Action::SetFen(x) => handle
                                 .write_all(format!("position fen {x}\n").as_bytes())
                                 .unwrap(),
+                            Action::SetOption(name, value) => handle
+                                .write_all(
+                                    format!("setoption name {name} value {value}\n").as_bytes(),
+                                )
+                                .unwrap(),
                             Action::Start => handle.write_all(b"go\n").unwrap(),
                             Action::Stop => {
                                 handle.write_all(b"stop\n").unwrap();
@@ -109,7 +179,11 @@ impl Engine {
                             }
                         },
                         // `Ok(None)` when channel is closed and no messages left in the queue
-                        Ok(None) => return,
+                        Ok(None) => {
+                            let _ = instance.kill();
+                            let _ = instance.wait();
+                            return;
+                        }
                         // `Err(e)` when there are no messages available, but channel is not yet closed
                         Err(_) => (),
                     }
@@ -127,6 +201,7 @@ impl Engine {
             sender: sync_sender,
             eval,
             options: Arc::new(Mutex::new(options)),
+            sent: Sent::default(),
         }
     }
 
@@ -135,6 +210,28 @@ impl Engine {
         let _ = block_on(self.sender.send(Action::SetFen(fen)));
     }
 
+    /// Sets the position to analyze.
+    /// `UCI_Chess960` is switched on for Chess960 games and off again for normal ones,
+    /// and `UCI_Variant` is set to the variant of the game, as long as the engine has the options.
+    /// Nothing is sent if the engine already has the position.
+    /// Returns why the engine can't analyze the position when that changes
+    pub fn set_position(&mut self, position: &Position) -> Option<String> {
+        let before = self.sent.unsupported.clone();
+        let options = &self.options;
+        let actions = self.sent.update(position, |name| {
+            options
+                .lock()
+                .is_ok_and(|options| options.iter().any(|x| x.name() == name))
+        });
+        for action in actions {
+            let _ = block_on(self.sender.send(action));
+        }
+        self.sent
+            .unsupported
+            .clone()
+            .filter(|_| self.sent.unsupported != before)
+    }
+
     /// Makes stockfish start analyzing the position
     pub fn start(&mut self) {
         let _ = block_on(self.sender.send(Action::Start));
@@ -165,7 +262,101 @@ impl Default for Engine {
 
 #[cfg(test)]
 mod test {
-    use crate::engine::Engine;
+    use super::{Action, Sent};
+    use crate::{
+        engine::Engine,
+        position::{Move, Position, Variant},
+    };
+
+    #[test]
+    fn chess960_option() {
+        let mut sent = Sent::default();
+        let start = Position::default().to_fen();
+        assert_eq!(
+            sent.update(&Position::default(), |_| true),
+            [Action::SetFen(start.clone())]
+        );
+        assert_eq!(sent.update(&Position::default(), |_| true), []);
+        let chess960 = Position::chess960(0).unwrap();
+        let switch_on = sent.update(&chess960, |_| true);
+        assert_eq!(
+            switch_on,
+            [
+                Action::SetOption("UCI_Chess960".to_string(), "true".to_string()),
+                Action::SetFen(chess960.to_fen()),
+            ]
+        );
+        // The option is only sent again when the flag changes back
+        let mut moved = Position::chess960(0).unwrap();
+        moved.make_move(*moved.get_moves().iter().next().unwrap());
+        assert_eq!(
+            sent.update(&moved, |_| true),
+            [Action::SetFen(moved.to_fen())]
+        );
+        assert_eq!(
+            sent.update(&Position::default(), |_| true),
+            [
+                Action::SetOption("UCI_Chess960".to_string(), "false".to_string()),
+                Action::SetFen(start.clone()),
+            ]
+        );
+        // Engines without the option stop instead of getting the position
+        let mut sent = Sent::default();
+        assert_eq!(
+            sent.update(&chess960, |name| name != "UCI_Chess960"),
+            [Action::Stop]
+        );
+        assert_eq!(
+            sent.unsupported.as_deref(),
+            Some("The engine doesn't support Chess960")
+        );
+        assert_eq!(
+            sent.update(&Position::default(), |name| name != "UCI_Chess960"),
+            [Action::SetFen(start)]
+        );
+    }
+
+    #[test]
+    fn variant_option() {
+        let mut sent = Sent::default();
+        let crazyhouse = Position::new(Variant::Crazyhouse);
+        assert_eq!(
+            sent.update(&crazyhouse, |_| true),
+            [
+                Action::SetOption("UCI_Variant".to_string(), "crazyhouse".to_string()),
+                Action::SetFen(crazyhouse.to_fen()),
+            ]
+        );
+        let mut moved = Position::new(Variant::Crazyhouse);
+        moved.make_move(Move::parse(&moved, "e4").unwrap());
+        assert_eq!(
+            sent.update(&moved, |_| true),
+            [Action::SetFen(moved.to_fen())]
+        );
+        let atomic = Position::new(Variant::Atomic);
+        assert_eq!(
+            sent.update(&atomic, |_| true)[0],
+            Action::SetOption("UCI_Variant".to_string(), "atomic".to_string())
+        );
+        assert_eq!(
+            sent.update(&Position::default(), |_| true)[0],
+            Action::SetOption("UCI_Variant".to_string(), "chess".to_string())
+        );
+        // Engines that only play standard chess stop instead of getting the position
+        let mut sent = Sent::default();
+        let standard = |name: &str| name != "UCI_Variant";
+        assert_eq!(sent.update(&atomic, standard), [Action::Stop]);
+        assert_eq!(sent.update(&atomic, standard), []);
+        assert_eq!(
+            sent.unsupported.as_deref(),
+            Some("The engine doesn't support Atomic")
+        );
+        assert_eq!(
+            sent.update(&Position::default(), standard),
+            [Action::SetFen(Position::default().to_fen())]
+        );
+        assert_eq!(sent.unsupported, None);
+    }
 
     #[test]
     fn stockfish() {
@@ -179,9 +370,11 @@ mod test {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum Action {
     SetFen(String),
+    /// The name and value of a uci option
+    SetOption(String, String),
     Start,
     Stop,
     Eval,
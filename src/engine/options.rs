@@ -8,6 +8,11 @@ pub struct Option {
 }
 
 impl Option {
+    /// The name the option is set by
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn parse(raw: &str) -> Self {
         let mut split = raw.split_whitespace();
         assert_eq!(split.next(), Some("option"), "Not parsing an option");
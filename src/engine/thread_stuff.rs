@@ -27,7 +27,7 @@ where
         &self.data
     }
 
-    pub fn new(data: T) -> Self {
+    pub const fn new(data: T) -> Self {
         Self {
             data,
             should_wait: Mutex::new(false),
@@ -0,0 +1,108 @@
+//! Reads perft suites in the epd style used by most engines and checks them against
+//! [`Position::perft`]
+
+use crate::position::{FenError, Move, Position};
+
+/// A position together with the node counts it should have at some depths
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftCase {
+    /// The position to count from
+    pub fen: String,
+    /// Pairs of depth and the expected number of nodes
+    pub expected: Vec<(usize, u64)>,
+}
+
+/// A depth at which [`Position::perft`] didn't return the expected count
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftMismatch {
+    /// The depth that was searched
+    pub depth: usize,
+    /// The count from the suite
+    pub expected: u64,
+    /// The count from the move generator
+    pub found: u64,
+    /// The count after every first move, to compare against a reference engine
+    pub divide: Vec<(Move, u64)>,
+}
+
+impl PerftCase {
+    /// Parses a line such as `<fen> ;D1 20 ;D2 400`.
+    /// The fen may leave out the move counters.
+    /// Returns `None` for blank lines, comments starting with `#` and malformed lines
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let mut fields = line.split(';');
+        let mut fen = fields.next()?.trim().to_string();
+        if fen.split_whitespace().count() == 4 {
+            fen.push_str(" 0 1");
+        }
+        let expected = fields
+            .map(|field| {
+                let (depth, nodes) = field.trim().split_once(char::is_whitespace)?;
+                Some((
+                    depth.strip_prefix('D')?.parse().ok()?,
+                    nodes.trim().parse().ok()?,
+                ))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { fen, expected })
+    }
+
+    /// Runs perft for every expected depth up to and including `max_depth`
+    /// # Errors
+    /// Returns why the fen couldn't be parsed
+    pub fn verify(&self, max_depth: usize) -> Result<Vec<PerftMismatch>, FenError> {
+        let pos = Position::try_from(self.fen.as_str())?;
+        Ok(self
+            .expected
+            .iter()
+            .filter(|(depth, _)| *depth <= max_depth)
+            .filter_map(|&(depth, expected)| {
+                let found = pos.perft(depth);
+                (found != expected).then(|| PerftMismatch {
+                    depth,
+                    expected,
+                    found,
+                    divide: pos.divide(depth),
+                })
+            })
+            .collect())
+    }
+}
+
+/// Parses every case in a suite, skipping lines that aren't cases
+#[must_use]
+pub fn parse_suite(data: &str) -> Vec<PerftCase> {
+    data.lines().filter_map(PerftCase::parse).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_suite, PerftCase};
+
+    #[test]
+    fn parse_case() {
+        assert_eq!(
+            PerftCase::parse("4k3/8/8/8/8/8/8/4K2R w K - ;D1 15 ;D2 66"),
+            Some(PerftCase {
+                fen: "4k3/8/8/8/8/8/8/4K2R w K - 0 1".to_string(),
+                expected: vec![(1, 15), (2, 66)],
+            })
+        );
+        assert_eq!(PerftCase::parse("# comment"), None);
+        assert_eq!(PerftCase::parse("8/8/8/8/8/8/8/8 w - - ;D1"), None);
+    }
+
+    #[test]
+    fn bundled_suite() {
+        let suite = parse_suite(include_str!("../data/perft.epd"));
+        assert_eq!(suite.len(), 15);
+        for case in suite {
+            assert_eq!(case.verify(3), Ok(Vec::new()), "{}", case.fen);
+        }
+    }
+}
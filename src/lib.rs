@@ -1,10 +1,5 @@
 //! Imports all modules
-#![deny(
-    missing_docs,
-    missing_abi,
-    missing_fragment_specifier,
-    missing_debug_implementations
-)]
+#![deny(missing_docs, missing_abi, missing_debug_implementations)]
 #![warn(clippy::pedantic, clippy::nursery)]
 
 pub mod app;
@@ -12,3 +7,5 @@ pub mod ui;
 pub mod errors;
 pub mod position;
 pub mod engine;
+pub mod database;
+pub mod epd;
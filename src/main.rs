@@ -1,7 +1,11 @@
 //! Crate to manage something. Haven't decided yet
 
-use chess_tui::{app::App, errors, ui::ui};
-use std::io;
+use chess_tui::{
+    app::App,
+    errors,
+    ui::{split_message, ui},
+};
+use std::{io, time::Duration};
 
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event},
@@ -25,6 +29,10 @@ fn main() -> color_eyre::Result<()> {
 
     // create app and run it
     let mut app = App::default();
+    // A PGN file given on the command line is opened in the game list
+    if let Some(path) = std::env::args_os().nth(1) {
+        app.open_database(path);
+    }
     let res = run_app(&mut terminal, &mut app);
 
     // restore terminal
@@ -50,6 +58,11 @@ where
     loop {
         terminal.draw(|f| ui(f, app))?;
 
+        // Waiting for events times out, so the screen keeps up with indexing
+        app.update();
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
         match event::read()? {
             Event::Key(key) => {
                 if key.kind == event::KeyEventKind::Release {
@@ -61,7 +74,9 @@ where
                 };
             }
             Event::Mouse(mouse) => {
-                if app.handle_mouse(mouse, &terminal.get_frame().size()) {
+                // The same part of the screen the app was drawn in
+                let (area, _) = split_message(app, terminal.get_frame().size());
+                if app.handle_mouse(mouse, &area) {
                     return Ok(());
                 }
             }
@@ -0,0 +1,205 @@
+//! Numeric annotation glyphs and the popup for editing comments
+
+use std::{fmt, str::FromStr};
+
+use crossterm::event::KeyCode;
+
+use super::tree::NodeId;
+
+/// A numeric annotation glyph, written `$n` in a PGN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Nag(pub u8);
+
+/// The glyphs with a symbol of their own. The first symbol is the one that is shown
+const SYMBOLS: [(u8, &[&str]); 14] = [
+    (1, &["!"]),
+    (2, &["?"]),
+    (3, &["!!"]),
+    (4, &["??"]),
+    (5, &["!?"]),
+    (6, &["?!"]),
+    (10, &["=", "=="]),
+    (13, &["∞"]),
+    (14, &["+=", "⩲"]),
+    (15, &["=+", "⩱"]),
+    (16, &["+/-", "±"]),
+    (17, &["-/+", "∓"]),
+    (18, &["+-"]),
+    (19, &["-+"]),
+];
+
+impl Nag {
+    /// A good move
+    pub const GOOD: Self = Self(1);
+    /// A mistake
+    pub const MISTAKE: Self = Self(2);
+    /// A brilliant move
+    pub const BRILLIANT: Self = Self(3);
+    /// A blunder
+    pub const BLUNDER: Self = Self(4);
+    /// An interesting move
+    pub const INTERESTING: Self = Self(5);
+    /// A dubious move
+    pub const DUBIOUS: Self = Self(6);
+    /// An equal position
+    pub const EQUAL: Self = Self(10);
+
+    /// Whether the glyph judges the move itself, like `!` or `?`.
+    /// These are written right after the move, without a space
+    #[must_use]
+    pub const fn is_move_assessment(self) -> bool {
+        matches!(self.0, 1..=9)
+    }
+
+    /// Whether the glyph judges the position, like `=` or `+-`
+    #[must_use]
+    pub const fn is_position_assessment(self) -> bool {
+        matches!(self.0, 10..=19)
+    }
+
+    /// Whether a move can't have both glyphs, because they judge the same thing
+    #[must_use]
+    pub const fn conflicts(self, other: Self) -> bool {
+        (self.is_move_assessment() && other.is_move_assessment())
+            || (self.is_position_assessment() && other.is_position_assessment())
+    }
+}
+
+impl fmt::Display for Nag {
+    /// Writes the symbol of the glyph, or `$n` if it doesn't have one
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match SYMBOLS.iter().find(|(nag, _)| *nag == self.0) {
+            Some((_, symbols)) => f.write_str(symbols[0]),
+            None => write!(f, "${}", self.0),
+        }
+    }
+}
+
+impl FromStr for Nag {
+    type Err = ();
+
+    /// Parses `$n` or one of the symbols, like `!?` or `+/-`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(number) = s.strip_prefix('$') {
+            return number.parse().map(Self).map_err(|_| ());
+        }
+        SYMBOLS
+            .iter()
+            .find(|(_, symbols)| symbols.contains(&s))
+            .map(|&(nag, _)| Self(nag))
+            .ok_or(())
+    }
+}
+
+/// A comment being edited in the popup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentEdit {
+    /// The node the comment belongs to
+    pub node: NodeId,
+    /// Whether it is the comment before the move instead of after it
+    pub before: bool,
+    /// The text so far
+    pub text: String,
+    /// Where typing inserts, in characters
+    pub cursor: usize,
+}
+
+/// What a key did to a [`CommentEdit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditResult {
+    /// The comment is still being edited
+    Editing,
+    /// The comment should be saved
+    Save,
+    /// The changes should be thrown away
+    Cancel,
+}
+
+impl CommentEdit {
+    /// Starts editing a comment with its current text
+    #[must_use]
+    pub fn new(node: NodeId, before: bool, text: &str) -> Self {
+        Self {
+            node,
+            before,
+            text: text.to_string(),
+            cursor: text.chars().count(),
+        }
+    }
+
+    /// The byte offset of the cursor in the text
+    fn offset(&self) -> usize {
+        self.text
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.text.len(), |(offset, _)| offset)
+    }
+
+    /// Edits the text like a single line text field. Enter saves and Esc cancels
+    pub fn handle_key(&mut self, code: KeyCode) -> EditResult {
+        match code {
+            KeyCode::Enter => return EditResult::Save,
+            KeyCode::Esc => return EditResult::Cancel,
+            KeyCode::Char(c) => {
+                let offset = self.offset();
+                self.text.insert(offset, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let offset = self.offset();
+                self.text.remove(offset);
+            }
+            KeyCode::Delete if self.cursor < self.text.chars().count() => {
+                let offset = self.offset();
+                self.text.remove(offset);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.text.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.chars().count(),
+            _ => (),
+        }
+        EditResult::Editing
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::KeyCode;
+
+    use super::{CommentEdit, EditResult, Nag};
+    use crate::position::GameTree;
+
+    #[test]
+    fn nags() {
+        for (text, nag) in [("!", 1), ("??", 4), ("!?", 5), ("+=", 14), ("-+", 19)] {
+            assert_eq!(text.parse(), Ok(Nag(nag)));
+            assert_eq!(Nag(nag).to_string(), text);
+        }
+        assert_eq!("±".parse(), Ok(Nag(16)));
+        assert_eq!("$146".parse(), Ok(Nag(146)));
+        assert_eq!(Nag(146).to_string(), "$146");
+        assert!("!!!".parse::<Nag>().is_err());
+        assert!(Nag::GOOD.conflicts(Nag::DUBIOUS));
+        assert!(!Nag::GOOD.conflicts(Nag::EQUAL));
+    }
+
+    #[test]
+    fn editing() {
+        let mut edit = CommentEdit::new(GameTree::ROOT, false, "ab");
+        edit.handle_key(KeyCode::Left);
+        edit.handle_key(KeyCode::Char('é'));
+        edit.handle_key(KeyCode::End);
+        edit.handle_key(KeyCode::Char('c'));
+        assert_eq!(edit.text, "aébc");
+        edit.handle_key(KeyCode::Home);
+        edit.handle_key(KeyCode::Delete);
+        edit.handle_key(KeyCode::Right);
+        edit.handle_key(KeyCode::Backspace);
+        assert_eq!(edit.text, "bc");
+        assert_eq!(edit.handle_key(KeyCode::Char('x')), EditResult::Editing);
+        assert_eq!(edit.handle_key(KeyCode::Enter), EditResult::Save);
+        assert_eq!(edit.handle_key(KeyCode::Esc), EditResult::Cancel);
+    }
+}
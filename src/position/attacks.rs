@@ -0,0 +1,124 @@
+//! Queries about which pieces attack, defend and pin each other
+
+use super::{moves::Square, Color, PieceType, Position};
+
+/// Extra information drawn on top of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overlay {
+    /// Only the pieces, the selected square and a king in check
+    #[default]
+    None,
+    /// Marks pieces that are attacked and not defended
+    Hanging,
+    /// Shows how many more white than black pieces attack each square
+    Control,
+}
+
+impl Overlay {
+    /// The overlay after this one, wrapping around to [`Overlay::None`]
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::None => Self::Hanging,
+            Self::Hanging => Self::Control,
+            Self::Control => Self::None,
+        }
+    }
+}
+
+impl Position {
+    /// The squares of the pieces of color `by` that attack `square`.
+    /// The square doesn't have to be empty or hold a piece of the other color, so this
+    /// also gives the defenders of a piece
+    #[must_use]
+    pub fn attackers(&self, square: Square, by: Color) -> Vec<Square> {
+        self.board.attackers(square, by).squares().collect()
+    }
+
+    /// Whether `color` is in check by the rules of the variant
+    #[must_use]
+    pub fn in_check(&self, color: Color) -> bool {
+        self.board.in_check(color)
+    }
+
+    /// The pieces of `color` pinned to their king by a bishop, rook or queen
+    #[must_use]
+    pub fn pinned(&self, color: Color) -> Vec<Square> {
+        self.board.pinned(color).squares().collect()
+    }
+
+    /// The pieces of `color` other than the king that are attacked and not defended
+    #[must_use]
+    pub fn hanging(&self, color: Color) -> Vec<Square> {
+        let king = self.board.pieces(PieceType::King);
+        (self.board.color(color) & !king)
+            .squares()
+            .filter(|&square| {
+                self.board.is_attacked(square, color.opposite())
+                    && !self.board.is_attacked(square, color)
+            })
+            .collect()
+    }
+
+    /// The overlay drawn on the board
+    #[must_use]
+    pub const fn overlay(&self) -> Overlay {
+        self.overlay
+    }
+
+    /// Changes the overlay drawn on the board
+    pub const fn set_overlay(&mut self, overlay: Overlay) {
+        self.overlay = overlay;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{Color, Position, Square};
+
+    fn squares(names: &[&str]) -> Vec<Square> {
+        let mut squares = names
+            .iter()
+            .map(|name| {
+                let mut chars = name.chars();
+                Square::from_chess_square([chars.next().unwrap(), chars.next().unwrap()])
+            })
+            .collect::<Vec<_>>();
+        squares.sort_by_key(|x| x.index());
+        squares
+    }
+
+    #[test]
+    fn attackers() {
+        let pos = Position::try_from("4k3/8/8/3p4/5N2/1B6/8/4RK2 b - - 0 1").unwrap();
+        let d5 = Square::new(4, 3);
+        let e4 = Square::new(3, 4);
+        assert_eq!(pos.attackers(d5, Color::White), squares(&["b3", "f4"]));
+        assert!(pos.attackers(d5, Color::Black).is_empty());
+        // Empty squares have attackers too
+        assert_eq!(pos.attackers(e4, Color::White), squares(&["e1"]));
+        assert_eq!(pos.attackers(e4, Color::Black), squares(&["d5"]));
+        // The rook sees all the way up to the king
+        assert!(pos.in_check(Color::Black));
+        assert!(!pos.in_check(Color::White));
+    }
+
+    #[test]
+    fn pins() {
+        // The bishop on d7 is pinned, the knight on f7 isn't because the pawn on g6 blocks
+        let pos = Position::try_from("4k3/3b1n2/6p1/1B5B/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(pos.pinned(Color::Black), squares(&["d7"]));
+        // Two pieces between the king and the rook aren't pinned
+        let pos = Position::try_from("4k3/4n3/4b3/8/8/8/8/4RK2 b - - 0 1").unwrap();
+        assert!(pos.pinned(Color::Black).is_empty());
+        assert!(pos.pinned(Color::White).is_empty());
+    }
+
+    #[test]
+    fn hanging() {
+        let pos = Position::try_from("4k3/8/8/3p4/4N3/1B6/8/5K2 w - - 0 1").unwrap();
+        assert_eq!(pos.hanging(Color::White), squares(&["e4"]));
+        assert_eq!(pos.hanging(Color::Black), squares(&["d5"]));
+        assert!(Position::default().hanging(Color::White).is_empty());
+    }
+}
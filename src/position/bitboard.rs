@@ -0,0 +1,480 @@
+//! Bitboards and the attack lookup tables built on them
+
+use std::{
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not},
+    sync::OnceLock,
+};
+
+use super::{moves::Square, Color};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+/// A set of squares, with bit `n` set if the square with [`Square::index`] `n` is in the set
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Self = Self(0);
+    /// The light squares, starting with b1
+    pub const LIGHT_SQUARES: Self = Self(0x55AA_55AA_55AA_55AA);
+
+    pub const fn from_square(square: Square) -> Self {
+        Self(1 << square.index())
+    }
+
+    pub const fn contains(self, square: Square) -> bool {
+        self.0 & (1 << square.index()) != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Gets the lowest square in the set
+    pub fn first(self) -> Option<Square> {
+        (!self.is_empty()).then(|| Square::from_index(self.0.trailing_zeros() as usize))
+    }
+
+    /// Iterates the squares from a1 to h8
+    pub fn squares(self) -> impl Iterator<Item = Square> {
+        let mut rest = self.0;
+        std::iter::from_fn(move || {
+            if rest == 0 {
+                return None;
+            }
+            let square = Square::from_index(rest.trailing_zeros() as usize);
+            rest &= rest - 1;
+            Some(square)
+        })
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self {
+        Self(self.0 ^ rhs.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0;
+    }
+}
+
+pub const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+pub const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Multipliers that map every relevant occupancy of a square to a slot with the
+/// right attacks. Found ahead of time with a random search
+const ROOK_MAGICS: [u64; 64] = [
+    0x9080_0011_8420_4004,
+    0x00C0_1008_A000_4000,
+    0x0500_0811_00C1_2000,
+    0x4700_0900_8420_3000,
+    0x9200_0600_6821_0410,
+    0x0080_0200_1400_0980,
+    0x0080_1A00_0100_5080,
+    0x0900_0180_2145_0002,
+    0x3000_8002_2040_0480,
+    0x2000_4000_4220_1000,
+    0x0008_8080_2000_9000,
+    0x8000_8010_0080_0800,
+    0x0901_0008_020C_1100,
+    0x0602_0010_1200_0884,
+    0x0441_0024_0200_0100,
+    0x0140_8002_4480_2100,
+    0x9220_6080_00C0_0090,
+    0x0090_0540_00C8_2000,
+    0x0001_0100_1040_6001,
+    0x4801_8480_1800_3000,
+    0x0404_0500_1008_0100,
+    0x0001_0100_0604_0008,
+    0x0001_8400_2805_3002,
+    0x0200_2200_0080_C104,
+    0x0408_2080_8004_4004,
+    0x0050_0040_C000_2002,
+    0x8D02_0022_0034_8040,
+    0x0000_0800_8010_0080,
+    0x8091_1045_0008_0100,
+    0x0802_0080_8014_0042,
+    0x0001_0005_0004_0600,
+    0x0090_018A_0000_C104,
+    0x0314_8140_0080_0420,
+    0x0401_8100_4200_2E00,
+    0x0241_4220_0100_1100,
+    0x0080_8010_0080_0802,
+    0x0080_0401_8080_0800,
+    0x4002_0108_0200_1004,
+    0x8002_0108_0400_1002,
+    0x200A_0100_4E00_0C84,
+    0x1002_81C0_1120_8008,
+    0x02C1_2000_5000_4000,
+    0x0400_3020_0101_0040,
+    0x0001_1000_0901_0020,
+    0x0001_2400_0800_8080,
+    0x002A_0020_1004_0400,
+    0x2420_2100_0200_8080,
+    0x088D_0401_C092_0021,
+    0x0001_0048_2082_0200,
+    0x0100_A900_8042_0600,
+    0x0A20_0021_1000_8880,
+    0x2080_1000_200D_0100,
+    0x0200_2400_0801_8080,
+    0x1002_0030_0804_0A00,
+    0x8424_234A_1008_2C00,
+    0x06A2_3104_8844_0200,
+    0x7009_0012_4280_0025,
+    0x2D0D_0220_4000_8051,
+    0x420B_9008_2001_0041,
+    0x0100_1408_9001_0021,
+    0x0900_4800_1005_0105,
+    0x0401_000C_0002_4801,
+    0x0082_1210_0801_0184,
+    0x8002_0104_0024_D086,
+];
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0008_0108_0484_0080,
+    0x0110_0200_B10A_0802,
+    0x0010_0410_81A0_2404,
+    0x3009_2042_0084_0000,
+    0x4044_2420_0104_8401,
+    0x0CA2_0250_0400_0082,
+    0xA900_4802_1010_0880,
+    0x8228_620A_0620_0621,
+    0x0C40_1092_3004_0180,
+    0x0004_0848_0CA0_8200,
+    0x0000_2842_4400_2198,
+    0x0210_180A_0024_0300,
+    0x8134_0C04_2180_0804,
+    0x1080_9110_4804_2400,
+    0x1900_88C1_0410_4010,
+    0x0001_4840_C404_2014,
+    0x0040_00A0_5802_0080,
+    0x2090_0C25_1102_0410,
+    0x1026_0084_0802_1100,
+    0x8002_0004_0362_0200,
+    0x0004_0000_81A0_8040,
+    0x5081_8001_1010_0100,
+    0x0003_0004_0118_0200,
+    0x0002_0041_0082_0301,
+    0x0410_1080_0C20_524B,
+    0x0461_0420_2118_0200,
+    0x0128_0100_1800_5300,
+    0x1002_0060_1200_8200,
+    0x1440_8400_0080_2004,
+    0x0001_8020_1202_1000,
+    0x0041_4402_0302_8806,
+    0x0000_4084_2042_0800,
+    0x1401_0411_9060_2000,
+    0x4000_8A10_1020_8422,
+    0x6304_0208_0005_0440,
+    0x0042_0084_000A_0210,
+    0x0051_0104_0002_0020,
+    0x0054_0800_A00A_0280,
+    0xC044_0104_0400_4404,
+    0x0100_8400_8208_4A00,
+    0x3008_0208_0400_6080,
+    0x0001_0910_900C_2200,
+    0x0110_4C00_4C00_0800,
+    0x0806_0020_1104_0808,
+    0x0004_2020_4090_0700,
+    0x3122_04A1_0200_8101,
+    0x0002_0801_0410_2300,
+    0x0404_1084_1140_2110,
+    0x0841_0090_1008_2004,
+    0xC414_2082_0820_0080,
+    0x4080_A200_4208_4000,
+    0x201C_0002_8404_4001,
+    0x082C_0010_2A02_1800,
+    0x1000_4004_0818_8208,
+    0x0210_1010_2148_4020,
+    0x0802_1802_208A_0084,
+    0x6211_0488_8405_4000,
+    0x4441_1201_0101_5000,
+    0x0014_0081_00A8_2404,
+    0x8020_0822_2020_8800,
+    0x0000_E004_1002_0610,
+    0x8008_0141_0819_0904,
+    0x0410_0C20_3802_0098,
+    0x4010_2000_A102_0022,
+];
+
+/// Builds a table with the squares reachable by a single step in any of `offsets`
+const fn step_table<const N: usize>(offsets: [(i8, i8); N]) -> [Bitboard; 64] {
+    let mut table = [Bitboard::EMPTY; 64];
+    let mut index = 0;
+    while index < 64 {
+        #[allow(clippy::cast_possible_truncation)]
+        let (row, col) = ((index / 8) as i8, (index % 8) as i8);
+        let mut i = 0;
+        while i < N {
+            let (r, c) = (row + offsets[i].0, col + offsets[i].1);
+            if r >= 0 && r < 8 && c >= 0 && c < 8 {
+                table[index].0 |= 1 << (r * 8 + c);
+            }
+            i += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+const KNIGHT_ATTACKS: [Bitboard; 64] = step_table(KNIGHT_OFFSETS);
+const KING_ATTACKS: [Bitboard; 64] = step_table(KING_OFFSETS);
+const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [
+    step_table([(1, -1), (1, 1)]),
+    step_table([(-1, -1), (-1, 1)]),
+];
+
+pub const fn knight_attacks(square: Square) -> Bitboard {
+    KNIGHT_ATTACKS[square.index()]
+}
+
+pub const fn king_attacks(square: Square) -> Bitboard {
+    KING_ATTACKS[square.index()]
+}
+
+/// The squares a pawn of `color` standing on `square` attacks
+pub const fn pawn_attacks(color: Color, square: Square) -> Bitboard {
+    PAWN_ATTACKS[color as usize][square.index()]
+}
+
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.rook[square.index()].lookup(&tables.attacks, occupied)
+}
+
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.bishop[square.index()].lookup(&tables.attacks, occupied)
+}
+
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+/// Every square on a line through both squares, including them.
+/// Empty if they don't share a rank, file or diagonal
+pub fn line(a: Square, b: Square) -> Bitboard {
+    tables().lines[a.index()][b.index()]
+}
+
+/// The squares strictly between two squares on a shared line
+pub fn between(a: Square, b: Square) -> Bitboard {
+    tables().between[a.index()][b.index()]
+}
+
+/// Slides from `square` in every direction until the edge or the first occupied square
+fn slide(square: Square, directions: [(i8, i8); 4], occupied: Bitboard) -> Bitboard {
+    let mut attacks = Bitboard::EMPTY;
+    for (dr, dc) in directions {
+        let mut curr = square;
+        while let Some(next) = curr.offset(dr, dc) {
+            attacks |= Bitboard::from_square(next);
+            if occupied.contains(next) {
+                break;
+            }
+            curr = next;
+        }
+    }
+    attacks
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Magic {
+    /// The squares whose occupancy matter, excluding the edges
+    mask: Bitboard,
+    multiplier: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    #[allow(clippy::cast_possible_truncation)]
+    fn index(&self, occupied: Bitboard) -> usize {
+        ((occupied & self.mask).0.wrapping_mul(self.multiplier) >> self.shift) as usize
+    }
+
+    fn lookup(&self, attacks: &[Bitboard], occupied: Bitboard) -> Bitboard {
+        attacks[self.offset + self.index(occupied)]
+    }
+}
+
+#[derive(Debug)]
+struct Tables {
+    rook: [Magic; 64],
+    bishop: [Magic; 64],
+    attacks: Vec<Bitboard>,
+    lines: Vec<[Bitboard; 64]>,
+    between: Vec<[Bitboard; 64]>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(Tables::new)
+}
+
+impl Tables {
+    fn new() -> Self {
+        let mut attacks = Vec::new();
+        let mut rook = [Magic::default(); 64];
+        let mut bishop = [Magic::default(); 64];
+        for index in 0..64 {
+            let square = Square::from_index(index);
+            rook[index] = magic_table(square, ROOK_DIRECTIONS, ROOK_MAGICS[index], &mut attacks);
+            bishop[index] = magic_table(
+                square,
+                BISHOP_DIRECTIONS,
+                BISHOP_MAGICS[index],
+                &mut attacks,
+            );
+        }
+        let mut lines = vec![[Bitboard::EMPTY; 64]; 64];
+        let mut between = vec![[Bitboard::EMPTY; 64]; 64];
+        for a in (0..64).map(Square::from_index) {
+            for directions in [ROOK_DIRECTIONS, BISHOP_DIRECTIONS] {
+                let from_a = slide(a, directions, Bitboard::EMPTY);
+                for b in from_a.squares() {
+                    let from_b = slide(b, directions, Bitboard::EMPTY);
+                    lines[a.index()][b.index()] =
+                        (from_a & from_b) | Bitboard::from_square(a) | Bitboard::from_square(b);
+                    between[a.index()][b.index()] = slide(a, directions, Bitboard::from_square(b))
+                        & slide(b, directions, Bitboard::from_square(a));
+                }
+            }
+        }
+        Self {
+            rook,
+            bishop,
+            attacks,
+            lines,
+            between,
+        }
+    }
+}
+
+/// Fills the slots of one square's magic lookup and appends them to `attacks`.
+/// Two occupancies may share a slot only if they result in the same attacks, which
+/// is what the multipliers in [`ROOK_MAGICS`] and [`BISHOP_MAGICS`] were searched for
+fn magic_table(
+    square: Square,
+    directions: [(i8, i8); 4],
+    multiplier: u64,
+    attacks: &mut Vec<Bitboard>,
+) -> Magic {
+    let edges = ((Bitboard(0xFF) | Bitboard(0xFF << 56)) & !Bitboard(0xFF << (square.row * 8)))
+        | ((Bitboard(0x0101_0101_0101_0101) | Bitboard(0x8080_8080_8080_8080))
+            & !Bitboard(0x0101_0101_0101_0101 << square.col));
+    let mask = slide(square, directions, Bitboard::EMPTY) & !edges;
+    let bits = mask.count();
+    let magic = Magic {
+        mask,
+        multiplier,
+        shift: 64 - bits,
+        offset: attacks.len(),
+    };
+    attacks.resize(magic.offset + (1 << bits), Bitboard::EMPTY);
+    // Visits every subset of the mask
+    let mut subset = 0u64;
+    loop {
+        let occupied = Bitboard(subset);
+        let slot = magic.offset + magic.index(occupied);
+        let result = slide(square, directions, occupied);
+        debug_assert!(attacks[slot].is_empty() || attacks[slot] == result);
+        attacks[slot] = result;
+        subset = subset.wrapping_sub(mask.0) & mask.0;
+        if subset == 0 {
+            break;
+        }
+    }
+    magic
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        bishop_attacks, rook_attacks, slide, Bitboard, BISHOP_DIRECTIONS, ROOK_DIRECTIONS,
+    };
+    use crate::position::moves::Square;
+
+    #[test]
+    fn magics_match_slow_attacks() {
+        let mut seed = 0x1234_5678_u64;
+        for _ in 0..2000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let occupied = Bitboard(seed & seed.rotate_left(17));
+            for square in (0..64).map(Square::from_index) {
+                assert_eq!(
+                    rook_attacks(square, occupied),
+                    slide(square, ROOK_DIRECTIONS, occupied)
+                );
+                assert_eq!(
+                    bishop_attacks(square, occupied),
+                    slide(square, BISHOP_DIRECTIONS, occupied)
+                );
+            }
+        }
+    }
+}
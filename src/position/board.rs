@@ -0,0 +1,476 @@
+//! The board model that move generation works on
+
+use super::{
+    bitboard::{
+        between, bishop_attacks, king_attacks, knight_attacks, line, pawn_attacks, queen_attacks,
+        rook_attacks, Bitboard,
+    },
+    moves::{Move, MoveKind, Square},
+    variant::Variant,
+    zobrist, CastingRights, Color, Piece, PieceType,
+};
+
+/// The pieces a pawn can promote to, in the order the promotion picker shows them
+pub(super) const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+/// The pieces that can be in a crazyhouse pocket
+pub(super) const POCKET_PIECES: [PieceType; 5] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Piece placement together with the state needed to generate moves
+pub struct Board {
+    /// Indexed by [`PieceType`]
+    pieces: [Bitboard; 6],
+    /// Indexed by [`Color`]
+    colors: [Bitboard; 2],
+    /// Indexed by [`Square::index`]. Kept in sync with the bitboards for fast lookups
+    squares: [Option<Piece>; 64],
+    pub turn: Color,
+    pub castling_rights: CastingRights,
+    pub en_passant: Option<Square>,
+    /// The rules moves are generated and played by
+    pub variant: Variant,
+    /// Crazyhouse pieces in hand, indexed by [`Color`] and then by [`PieceType`] minus one
+    pockets: [[u8; 5]; 2],
+    /// Crazyhouse pieces that were promoted, which go back into a pocket as pawns
+    promoted: Bitboard,
+    /// Three-check: how many times each [`Color`] gave check
+    checks: [u8; 2],
+    /// The zobrist hash of the board, updated with every change
+    key: u64,
+}
+
+impl Board {
+    /// A board without any pieces, with white to move
+    pub const fn empty() -> Self {
+        Self {
+            pieces: [Bitboard::EMPTY; 6],
+            colors: [Bitboard::EMPTY; 2],
+            squares: [None; 64],
+            turn: Color::White,
+            castling_rights: CastingRights::NONE,
+            en_passant: None,
+            variant: Variant::Standard,
+            pockets: [[0; 5]; 2],
+            promoted: Bitboard::EMPTY,
+            checks: [0; 2],
+            key: 0,
+        }
+    }
+
+    /// Gets the piece on a square
+    pub const fn get(&self, square: Square) -> Option<Piece> {
+        self.squares[square.index()]
+    }
+
+    /// Places a piece on a square, or clears it
+    pub fn set(&mut self, square: Square, piece: Option<Piece>) {
+        let bit = Bitboard::from_square(square);
+        if let Some(old) = self.get(square) {
+            self.pieces[old.piece_type as usize] ^= bit;
+            self.colors[old.color as usize] ^= bit;
+            self.key ^= zobrist::piece(old, square);
+        }
+        if let Some(new) = piece {
+            self.pieces[new.piece_type as usize] |= bit;
+            self.colors[new.color as usize] |= bit;
+            self.key ^= zobrist::piece(new, square);
+        }
+        self.squares[square.index()] = piece;
+    }
+
+    /// The zobrist hash of the board
+    pub const fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// Recalculates the hash.
+    /// Needed after changing the side to move, castling rights or en passant square directly
+    pub fn update_key(&mut self) {
+        self.key = self.compute_key();
+    }
+
+    /// Calculates the zobrist hash from scratch
+    pub fn compute_key(&self) -> u64 {
+        let pockets = [Color::White, Color::Black]
+            .into_iter()
+            .flat_map(|color| {
+                POCKET_PIECES
+                    .into_iter()
+                    .map(move |piece| zobrist::pocket(color, piece, self.pocket(color, piece)))
+                    .chain(std::iter::once(zobrist::checks(color, self.checks(color))))
+            })
+            .fold(zobrist::promoted(self.promoted), |a, b| a ^ b);
+        (0..64)
+            .map(Square::from_index)
+            .filter_map(|square| Some(zobrist::piece(self.get(square)?, square)))
+            .fold(self.state_key() ^ pockets, |key, x| key ^ x)
+    }
+
+    /// Changes the side to move, castling rights or en passant square through `f`
+    /// and keeps the hash up to date
+    pub fn change_state(&mut self, f: impl FnOnce(&mut Self)) {
+        self.key ^= self.state_key();
+        f(self);
+        self.key ^= self.state_key();
+    }
+
+    /// How many pieces of a type a color has in its crazyhouse pocket
+    pub const fn pocket(&self, color: Color, piece_type: PieceType) -> u8 {
+        match piece_type {
+            PieceType::King => 0,
+            _ => self.pockets[color as usize][piece_type as usize - 1],
+        }
+    }
+
+    /// Sets how many pieces of a type a color has in its pocket. Kings can't be in a pocket
+    pub fn set_pocket(&mut self, color: Color, piece_type: PieceType, count: u8) {
+        if piece_type == PieceType::King {
+            return;
+        }
+        let old = self.pocket(color, piece_type);
+        self.key ^=
+            zobrist::pocket(color, piece_type, old) ^ zobrist::pocket(color, piece_type, count);
+        self.pockets[color as usize][piece_type as usize - 1] = count;
+    }
+
+    /// The crazyhouse pieces that were promoted
+    pub const fn promoted(&self) -> Bitboard {
+        self.promoted
+    }
+
+    /// Marks which crazyhouse pieces were promoted
+    pub fn set_promoted(&mut self, promoted: Bitboard) {
+        self.key ^= zobrist::promoted(self.promoted) ^ zobrist::promoted(promoted);
+        self.promoted = promoted;
+    }
+
+    /// How many times a color gave check in three-check
+    pub const fn checks(&self, color: Color) -> u8 {
+        self.checks[color as usize]
+    }
+
+    /// Sets how many times a color gave check
+    pub const fn set_checks(&mut self, color: Color, count: u8) {
+        self.key ^= zobrist::checks(color, self.checks(color)) ^ zobrist::checks(color, count);
+        self.checks[color as usize] = count;
+    }
+
+    /// The part of the hash that doesn't come from the pieces.
+    /// The en passant square is only included if a pawn can take on it, so positions
+    /// that only differ by an unusable en passant square count as repetitions
+    fn state_key(&self) -> u64 {
+        let (kingside_white, queenside_white) = self.castling_rights.get(Color::White);
+        let (kingside_black, queenside_black) = self.castling_rights.get(Color::Black);
+        let castling = [
+            kingside_white,
+            queenside_white,
+            kingside_black,
+            queenside_black,
+        ]
+        .into_iter()
+        .zip(zobrist::CASTLING)
+        .filter_map(|(right, key)| right.map(|_| key))
+        .fold(0, |a, b| a ^ b);
+        let en_passant = self
+            .en_passant
+            .filter(|&square| {
+                !(pawn_attacks(self.turn.opposite(), square)
+                    & self.colored(PieceType::Pawn, self.turn))
+                .is_empty()
+            })
+            .map_or(0, zobrist::en_passant);
+        zobrist::side(self.turn) ^ castling ^ en_passant
+    }
+
+    /// Every square with a piece of the given type, regardless of color
+    pub const fn pieces(&self, piece_type: PieceType) -> Bitboard {
+        self.pieces[piece_type as usize]
+    }
+
+    /// Every square with a piece of the given color
+    pub const fn color(&self, color: Color) -> Bitboard {
+        self.colors[color as usize]
+    }
+
+    /// Every square with a piece of the given type and color
+    pub fn colored(&self, piece_type: PieceType, color: Color) -> Bitboard {
+        self.pieces(piece_type) & self.color(color)
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.color(Color::White) | self.color(Color::Black)
+    }
+
+    pub fn king(&self, color: Color) -> Option<Square> {
+        self.colored(PieceType::King, color).first()
+    }
+
+    /// All pieces of color `by` attacking `square` when the board has the given occupancy
+    pub fn attackers_with(&self, square: Square, by: Color, occupied: Bitboard) -> Bitboard {
+        let diagonal = self.pieces(PieceType::Bishop) | self.pieces(PieceType::Queen);
+        let straight = self.pieces(PieceType::Rook) | self.pieces(PieceType::Queen);
+        // A pawn attacks the squares a pawn of the other color would attack from there
+        ((pawn_attacks(by.opposite(), square) & self.pieces(PieceType::Pawn))
+            | (knight_attacks(square) & self.pieces(PieceType::Knight))
+            | (king_attacks(square) & self.pieces(PieceType::King))
+            | (bishop_attacks(square, occupied) & diagonal)
+            | (rook_attacks(square, occupied) & straight))
+            & self.color(by)
+    }
+
+    /// All pieces of color `by` attacking `square`
+    pub fn attackers(&self, square: Square, by: Color) -> Bitboard {
+        self.attackers_with(square, by, self.occupied())
+    }
+
+    /// The pieces of `color` that can't leave the line between their king and an enemy slider
+    pub fn pinned(&self, color: Color) -> Bitboard {
+        let Some(king) = self.king(color) else {
+            return Bitboard::EMPTY;
+        };
+        let enemy = color.opposite();
+        let diagonal = self.pieces(PieceType::Bishop) | self.pieces(PieceType::Queen);
+        let straight = self.pieces(PieceType::Rook) | self.pieces(PieceType::Queen);
+        // Sliders that would attack the king if only the enemy pieces were on the board
+        let snipers = ((bishop_attacks(king, self.color(enemy)) & diagonal)
+            | (rook_attacks(king, self.color(enemy)) & straight))
+            & self.color(enemy);
+        snipers
+            .squares()
+            .map(|sniper| between(king, sniper) & self.occupied())
+            .filter(|blockers| blockers.count() == 1)
+            .fold(Bitboard::EMPTY, |pinned, blockers| pinned | blockers)
+            & self.color(color)
+    }
+
+    /// Checks whether `square` is attacked by any piece of color `by`
+    pub fn is_attacked(&self, square: Square, by: Color) -> bool {
+        !self.attackers_with(square, by, self.occupied()).is_empty()
+    }
+
+    /// Checks whether `color` is in check by the rules of the variant
+    pub fn in_check(&self, color: Color) -> bool {
+        self.variant.rules().in_check(self, color)
+    }
+
+    /// Checks whether the king of `color` is attacked
+    pub fn king_attacked(&self, color: Color) -> bool {
+        self.king(color)
+            .is_some_and(|king| self.is_attacked(king, color.opposite()))
+    }
+
+    /// The squares a piece attacks from `square`, given the current occupancy
+    fn attacks(&self, piece: Piece, square: Square) -> Bitboard {
+        match piece.piece_type {
+            PieceType::Pawn => pawn_attacks(piece.color, square),
+            PieceType::Knight => knight_attacks(square),
+            PieceType::King => king_attacks(square),
+            PieceType::Bishop => bishop_attacks(square, self.occupied()),
+            PieceType::Rook => rook_attacks(square, self.occupied()),
+            PieceType::Queen => queen_attacks(square, self.occupied()),
+        }
+    }
+
+    /// All moves that follow the movement rules, ignoring whether the own king is left in check
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        let own = self.color(self.turn);
+        for start in own.squares() {
+            let Some(piece) = self.get(start) else {
+                continue;
+            };
+            if piece.piece_type == PieceType::Pawn {
+                self.pawn_moves(start, &mut moves);
+                continue;
+            }
+            moves.extend(
+                (self.attacks(piece, start) & !own)
+                    .squares()
+                    .map(|end| Move::new(start, end)),
+            );
+            if piece.piece_type == PieceType::King {
+                self.castling_moves(start, &mut moves);
+            }
+        }
+        moves
+    }
+
+    fn pawn_moves(&self, start: Square, moves: &mut Vec<Move>) {
+        let (forward, home_row, last_row) = match self.turn {
+            Color::White => (1, 1, 7),
+            Color::Black => (-1, 6, 0),
+        };
+        let mut push = |end: Square| {
+            if end.row == last_row {
+                moves.extend(
+                    PROMOTIONS
+                        .into_iter()
+                        .map(|promotion| Move::new(start, end).with_promotion(promotion)),
+                );
+            } else {
+                moves.push(Move::new(start, end));
+            }
+        };
+        let empty = !self.occupied();
+        if let Some(one) = start.offset(forward, 0).filter(|&x| empty.contains(x)) {
+            push(one);
+            if start.row == home_row {
+                if let Some(two) = one.offset(forward, 0).filter(|&x| empty.contains(x)) {
+                    push(two);
+                }
+            }
+        }
+        let attacks = pawn_attacks(self.turn, start);
+        (attacks & self.color(self.turn.opposite()))
+            .squares()
+            .for_each(push);
+        if let Some(en_passant) = self.en_passant.filter(|&x| attacks.contains(x)) {
+            moves.push(Move::new(start, en_passant).with_kind(MoveKind::EnPassant));
+        }
+    }
+
+    /// Castling is encoded as the king taking its own rook, which works for Chess960 too.
+    /// The king ends up on the g or c file and the rook next to it on the inside
+    fn castling_moves(&self, king: Square, moves: &mut Vec<Move>) {
+        let row = match self.turn {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+        if king.row != row {
+            return;
+        }
+        let (kingside, queenside) = self.castling_rights.get(self.turn);
+        let rooks = self.colored(PieceType::Rook, self.turn);
+        let enemy = self.turn.opposite();
+        let mut try_castle = |rook_col: u8, king_col: u8, rook_end_col: u8| {
+            let rook = Square::new(row, rook_col);
+            let king_end = Square::new(row, king_col);
+            let rook_end = Square::new(row, rook_end_col);
+            // The king and rook only need each other out of the way
+            let others =
+                self.occupied() ^ Bitboard::from_square(king) ^ Bitboard::from_square(rook);
+            let path = between(king, king_end)
+                | between(rook, rook_end)
+                | Bitboard::from_square(king_end)
+                | Bitboard::from_square(rook_end);
+            let king_path = between(king, king_end)
+                | Bitboard::from_square(king)
+                | Bitboard::from_square(king_end);
+            if rooks.contains(rook)
+                && (path & others).is_empty()
+                && !king_path
+                    .squares()
+                    .any(|x| !self.attackers_with(x, enemy, others).is_empty())
+            {
+                moves.push(Move::new(king, rook).with_kind(MoveKind::Castle));
+            }
+        };
+        if let Some(col) = kingside {
+            try_castle(col, 6, 5);
+        }
+        if let Some(col) = queenside {
+            try_castle(col, 2, 3);
+        }
+    }
+
+    /// Every legal move by the rules of the variant
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.variant.rules().legal_moves(self)
+    }
+
+    /// Every move that doesn't leave the own king in check
+    pub fn standard_legal_moves(&self) -> Vec<Move> {
+        let Some(king) = self.king(self.turn) else {
+            return self.pseudo_legal_moves();
+        };
+        let in_check = self.king_attacked(self.turn);
+        let enemy = self.turn.opposite();
+        let mut moves = self.pseudo_legal_moves();
+        moves.retain(|&r#move| {
+            // Castling moves are fully checked when they are generated
+            if r#move.kind == MoveKind::Castle {
+                return true;
+            }
+            if r#move.start == king {
+                // The king itself mustn't be in the way of the attacks it is moving away from
+                let occupied = self.occupied() ^ Bitboard::from_square(king);
+                return self.attackers_with(r#move.end, enemy, occupied).is_empty();
+            }
+            // Only a piece on a line with the king can uncover an attack on it
+            if !in_check
+                && r#move.kind != MoveKind::EnPassant
+                && line(king, r#move.start).is_empty()
+            {
+                return true;
+            }
+            let mut next = *self;
+            next.standard_play(r#move);
+            !next.king_attacked(self.turn)
+        });
+        moves
+    }
+
+    /// Plays a move by the rules of the variant without checking whether it's legal
+    pub fn play(&mut self, r#move: Move) {
+        self.variant.rules().play(self, r#move);
+    }
+
+    /// Plays a move by the standard rules without checking whether it's legal
+    pub fn standard_play(&mut self, r#move: Move) {
+        let Some(piece) = self.get(r#move.start) else {
+            return;
+        };
+        let (start, end) = (r#move.start, r#move.end);
+        self.key ^= self.state_key();
+        match r#move.kind {
+            MoveKind::EnPassant => {
+                self.set(Square::new(start.row, end.col), None);
+            }
+            MoveKind::Castle => {
+                let (king_col, rook_col) = if end.col > start.col { (6, 5) } else { (2, 3) };
+                let rook = self.get(end);
+                // The king may land where the rook was and the other way around
+                self.set(start, None);
+                self.set(end, None);
+                self.set(Square::new(start.row, rook_col), rook);
+                self.set(Square::new(start.row, king_col), Some(piece));
+                self.castling_rights.remove(piece.color);
+                self.en_passant = None;
+                self.turn = self.turn.opposite();
+                self.key ^= self.state_key();
+                return;
+            }
+            MoveKind::Normal | MoveKind::Drop(_) => (),
+        }
+        self.en_passant = (piece.piece_type == PieceType::Pawn && start.row.abs_diff(end.row) == 2)
+            .then(|| Square::new(u8::midpoint(start.row, end.row), start.col));
+        if piece.piece_type == PieceType::King {
+            self.castling_rights.remove(piece.color);
+        }
+        self.castling_rights.update(start);
+        self.castling_rights.update(end);
+        self.set(start, None);
+        self.set(
+            end,
+            Some(
+                r#move
+                    .promotion
+                    .map_or(piece, |x| Piece::new(x, piece.color)),
+            ),
+        );
+        self.turn = self.turn.opposite();
+        self.key ^= self.state_key();
+    }
+}
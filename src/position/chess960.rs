@@ -0,0 +1,180 @@
+//! Creates the starting positions of Chess960
+
+use super::Position;
+
+/// Where the two knights go among the five squares left after placing the bishops and queen
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+impl Position {
+    /// Creates the Chess960 starting position with the given number, using the standard
+    /// numbering where 518 is the normal starting position.
+    /// Returns `None` if the number isn't below 960
+    #[must_use]
+    pub fn chess960(id: u16) -> Option<Self> {
+        if id >= 960 {
+            return None;
+        }
+        let mut id = usize::from(id);
+        let mut rank = [None; 8];
+        // One bishop on a light and one on a dark square
+        rank[id % 4 * 2 + 1] = Some('B');
+        id /= 4;
+        rank[id % 4 * 2] = Some('B');
+        id /= 4;
+        let mut place = |n: usize, piece: char| {
+            let file = (0..8)
+                .filter(|&x| rank[x].is_none())
+                .nth(n)
+                .unwrap_or_default();
+            rank[file] = Some(piece);
+        };
+        place(id % 6, 'Q');
+        id /= 6;
+        let (first, second) = KNIGHTS[id];
+        // The second knight skips the square the first one took
+        place(first, 'N');
+        place(second - 1, 'N');
+        // The king is always between the rooks
+        for piece in ['R', 'K', 'R'] {
+            place(0, piece);
+        }
+        let white = rank.iter().flatten().collect::<String>();
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{white} w KQkq - 0 1",
+            white.to_ascii_lowercase()
+        );
+        let mut pos = Self::try_from(fen).ok()?;
+        pos.chess960 = true;
+        Some(pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::position::{perft::check_perft, Editor, Move, MoveKind, Position, Square, Variant};
+
+    #[test]
+    fn start_positions() {
+        assert_eq!(
+            Position::chess960(518).unwrap().to_fen(),
+            Position::default().to_fen()
+        );
+        assert_eq!(
+            Position::chess960(0).unwrap().to_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert!(Position::chess960(960).is_none());
+        let placements = (0..960)
+            .map(|id| {
+                let pos = Position::chess960(id).unwrap();
+                assert!(pos.is_chess960());
+                pos.to_fen()
+            })
+            .collect::<HashSet<_>>();
+        assert_eq!(placements.len(), 960);
+    }
+
+    #[test]
+    fn shredder_and_x_fen() {
+        let fen = "rk2r3/8/8/8/8/8/8/RK2R3 w KQkq - 0 1";
+        let pos = Position::try_from(fen).unwrap();
+        assert!(pos.is_chess960());
+        assert_eq!(pos.to_fen(), fen);
+        assert_eq!(
+            pos.to_shredder_fen(),
+            "rk2r3/8/8/8/8/8/8/RK2R3 w EAea - 0 1"
+        );
+        assert_eq!(
+            Position::try_from(pos.to_shredder_fen()).unwrap().to_fen(),
+            fen
+        );
+        // With two rooks on the same side only the inner one needs its file
+        let inner = "4k3/8/8/8/8/8/8/1R2K1RR w GB - 0 1";
+        let pos = Position::try_from(inner).unwrap();
+        assert_eq!(pos.to_fen(), "4k3/8/8/8/8/8/8/1R2K1RR w GQ - 0 1");
+        assert!(!Position::default().is_chess960());
+    }
+
+    #[test]
+    fn flag_survives_round_trips() {
+        // The 518th setup is the normal one, so only the flag says it is Chess960
+        let pos = Position::chess960(518).unwrap();
+        assert!(pos.is_chess960());
+        let pgn = pos.to_pgn();
+        assert!(pgn.contains("[Variant \"Chess960\"]"));
+        let read = Position::from_pgn(&pgn).unwrap();
+        assert!(read.is_chess960());
+        assert_eq!(read.to_pgn(), pgn);
+        assert!(Editor::from(&pos).position().unwrap().is_chess960());
+        assert!(!Editor::default().position().unwrap().is_chess960());
+    }
+
+    #[test]
+    fn castling() {
+        // The king on f1 and rook on g1 swap places when castling kingside
+        let mut pos = Position::try_from("4k3/8/8/8/8/8/8/R4KR1 w AG - 0 1").unwrap();
+        let castle = Move::parse(&pos, "O-O").unwrap();
+        assert_eq!(castle.kind, MoveKind::Castle);
+        assert_eq!(castle.to_uci(true), "f1g1");
+        assert_eq!(castle.to_uci(false), "f1g1");
+        // The king taking its own rook is castling too
+        assert_eq!(Move::parse_san(&pos, "Kxg1"), Ok(castle));
+        // A king move to g1 would land on the rook, so only the rook click castles
+        assert_eq!(
+            pos.moves_between(Square::new(0, 5), Square::new(0, 6)),
+            vec![castle]
+        );
+        pos.make_move(castle);
+        assert_eq!(pos.to_fen(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+
+        // King takes rook input
+        let pos = Position::try_from("4k3/8/8/8/8/8/8/1R2K2R w HB - 0 1").unwrap();
+        let long = Move::parse(&pos, "e1b1").unwrap();
+        assert_eq!(long.kind, MoveKind::Castle);
+        assert_eq!(long.to_uci(false), "e1c1");
+        assert_eq!(Move::parse(&pos, "e1c1"), Ok(long));
+        assert_eq!(long.get_notation(&pos).as_deref(), Some("O-O-O"));
+
+        // The rook on b1 hides the attack of the queen on a1 until it moves
+        let pos = Position::try_from("q3k3/8/8/8/8/8/8/qR3K2 w B - 0 1").unwrap();
+        assert!(Move::parse(&pos, "O-O-O").is_err());
+    }
+
+    #[test]
+    fn perft() {
+        let cases = [
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                [21, 528, 12189],
+            ),
+            (
+                "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+                [21, 807, 18002],
+            ),
+            (
+                "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+                [20, 479, 10471],
+            ),
+            (
+                "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+                [22, 593, 13440],
+            ),
+        ];
+        for (fen, expected) in cases {
+            check_perft(Variant::Standard, fen, &expected);
+        }
+    }
+}
@@ -4,96 +4,781 @@ use ratatui::{
     layout::{Constraint, Layout, Rect},
     style::{Color, Style},
     text::{Line, Span},
-    widgets::Paragraph,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
 
-use super::{Position, ScreenLayout};
+use std::cmp::Ordering;
+
+use super::{
+    attacks::Overlay,
+    board::{POCKET_PIECES, PROMOTIONS},
+    moves::{MoveKind, Square},
+    shapes::Shape,
+    tree::{GameTree, NodeId},
+    variant::Variant,
+    Piece, PieceType, Position, ScreenLayout,
+};
+
+/// Where the promotion picker is drawn on top of a small board.
+/// It is one line of pieces inside a border, each piece two columns wide like a square
+pub(super) const fn promotion_area(board: Rect) -> Rect {
+    Rect {
+        x: board.x + 3,
+        y: board.y + 3,
+        width: 10,
+        height: 3,
+    }
+}
+
+impl ScreenLayout {
+    /// How many lines and columns a square of the board takes up
+    const fn square_size(self) -> (u16, u16) {
+        match self {
+            Self::Small => (1, 2),
+            Self::Large => (3, 7),
+        }
+    }
+
+    /// The text of a square, one entry per line: the piece with a space after it on
+    /// a small board and the piece in ascii art with a space around it on a large one
+    fn square_text(self, piece: Option<PieceType>) -> Vec<String> {
+        match self {
+            Self::Small => vec![format!("{} ", piece.map_or(' ', PieceType::to_unicode))],
+            Self::Large => piece
+                .map_or([""; 3], art)
+                .iter()
+                .map(|line| format!(" {line:5} "))
+                .collect(),
+        }
+    }
+}
+
+/// A piece in ascii art, five columns wide
+const fn art(piece: PieceType) -> [&'static str; 3] {
+    match piece {
+        PieceType::King => ["  +  ", " )_( ", " /_\\ "],
+        PieceType::Queen => [" \\^/ ", " )_( ", " /_\\ "],
+        PieceType::Rook => [" |=| ", " | | ", " /_\\ "],
+        PieceType::Bishop => ["  o  ", " (/) ", " /_\\ "],
+        PieceType::Knight => ["  __ ", " /o ]", " /_\\ "],
+        PieceType::Pawn => ["     ", "  o  ", " /_\\ "],
+    }
+}
+
+/// The eight ranks of a board, from the eighth rank down, with one square highlighted.
+/// Each rank is as many lines as a square is high, with one span per square in each
+pub(super) fn board_lines(
+    piece_at: impl Fn(Square) -> Option<Piece>,
+    highlighted: Option<Square>,
+    layout: ScreenLayout,
+) -> Vec<Line<'static>> {
+    (0..8)
+        .rev()
+        .flat_map(|row| {
+            let squares = (0..8)
+                .map(|col| {
+                    let square = Square::new(row, col);
+                    let piece = piece_at(square);
+                    let light = (row + col) & 1 == 1;
+                    let bg = match (light, highlighted == Some(square)) {
+                        (true, false) => Color::Blue,
+                        (true, true) => Color::LightBlue,
+                        (false, false) => Color::Green,
+                        (false, true) => Color::LightGreen,
+                    };
+                    let style = Style::new()
+                        .bg(bg)
+                        .fg(piece.map_or(Color::Green, |x| x.color.color()));
+                    (layout.square_text(piece.map(|x| x.piece_type)), style)
+                })
+                .collect::<Vec<_>>();
+            (0..usize::from(layout.square_size().0)).map(move |line| {
+                Line::from(
+                    squares
+                        .iter()
+                        .map(|(text, style)| Span::styled(text[line].clone(), *style))
+                        .collect::<Vec<_>>(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Where continued rows of a long variation start
+const MOVES_INDENT: &str = "       ";
+
+/// Some text of the move list
+struct MoveText {
+    text: String,
+    /// The node of the move the text shows, if it is a move
+    node: Option<NodeId>,
+    /// Whether the text is part of a comment
+    comment: bool,
+}
+
+impl MoveText {
+    fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            node: None,
+            comment: false,
+        }
+    }
+
+    const fn r#move(text: String, node: NodeId) -> Self {
+        Self {
+            text,
+            node: Some(node),
+            comment: false,
+        }
+    }
+}
+
+/// Adds a comment word by word, so long comments can wrap
+fn push_comment(comment: &str, line: &mut Vec<MoveText>) {
+    line.extend(comment.split_whitespace().map(|word| MoveText {
+        text: format!(" {word}"),
+        node: None,
+        comment: true,
+    }));
+}
+
+/// Where the parts of a layout go
+struct Areas {
+    board: Rect,
+    material: Rect,
+    variant: Rect,
+    status: Rect,
+    moves: Rect,
+    fen: Rect,
+}
+
+/// The spans of a square in the lines from [`board_lines`], one for each of its lines
+fn square_spans<'a>(
+    lines: &'a mut [Line<'static>],
+    square: Square,
+    layout: ScreenLayout,
+) -> impl Iterator<Item = &'a mut Span<'static>> {
+    let height = usize::from(layout.square_size().0);
+    lines
+        .iter_mut()
+        .skip(usize::from(7 - square.row) * height)
+        .take(height)
+        .map(move |line| &mut line.spans[usize::from(square.col)])
+}
+
+/// The span in the middle of a square, where arrows are drawn
+fn middle_span<'a>(
+    lines: &'a mut [Line<'static>],
+    square: Square,
+    layout: ScreenLayout,
+) -> &'a mut Span<'static> {
+    let height = usize::from(layout.square_size().0);
+    &mut lines[usize::from(7 - square.row) * height + height / 2].spans[usize::from(square.col)]
+}
+
+/// Replaces the last character of a span, which is a space next to the piece
+fn set_last(span: &mut Span<'static>, c: char) {
+    let mut content = span.content.to_string();
+    content.pop();
+    content.push(c);
+    span.content = content.into();
+}
+
 impl Position {
     /// Draws a chess-board inside of a rect
     pub fn draw(&self, frame: &mut Frame, chunk: Rect, layout: ScreenLayout) {
-        match layout {
-            ScreenLayout::Small => {
-                // The longest possible move I would have to format is 999. Nb8xc6+ Ne5xc6+
-                // NOTE: Doesn't allow for more than 1000 moves natively
-                let vertical =
-                    Layout::vertical([Constraint::Min(8), Constraint::Min(1)]).split(chunk);
-                let chunks = Layout::horizontal([
-                    Constraint::Length(16),
-                    Constraint::Max(2),
-                    Constraint::Min(20),
-                ])
-                .split(vertical[0]);
-                self.render_small_board(frame, chunks[0]);
-                self.render_moves(frame, chunks[2]);
-                frame.render_widget(Line::from(Span::raw(&self.starting_position)), vertical[1]);
+        let areas = self.areas(chunk, layout);
+        self.render_board(frame, areas.board, layout);
+        self.render_promotion(frame, areas.board);
+        self.render_material(frame, areas.material);
+        self.render_variant(frame, areas.variant);
+        self.render_status(frame, areas.status);
+        self.render_moves(frame, areas.moves);
+        self.render_comment_edit(frame, areas.moves);
+        self.render_move_entry(frame, areas.moves);
+        frame.render_widget(Line::from(Span::raw(self.to_fen())), areas.fen);
+    }
+
+    /// Splits the screen into the board with the lines under it, the moves and the fen
+    fn areas(&self, chunk: Rect, layout: ScreenLayout) -> Areas {
+        let (height, width) = layout.square_size();
+        let (height, width) = (8 * height, 8 * width);
+        // The board, the material lines and the variant lines all have to fit
+        let vertical = Layout::vertical([
+            Constraint::Min(height + 2 + self.variant_lines()),
+            Constraint::Min(1),
+        ])
+        .split(chunk);
+        let chunks = Layout::horizontal([
+            Constraint::Length(width),
+            Constraint::Max(2),
+            Constraint::Min(20),
+        ])
+        .split(vertical[0]);
+        let board = Layout::vertical([
+            Constraint::Length(height),
+            Constraint::Length(2),
+            Constraint::Length(self.variant_lines()),
+            Constraint::Min(0),
+        ])
+        .split(chunks[0]);
+        Areas {
+            board: board[0],
+            material: board[1],
+            variant: board[2],
+            status: board[3],
+            moves: chunks[2],
+            fen: vertical[1],
+        }
+    }
+
+    /// Draws the board with the arrows and colored squares of the move,
+    /// a king in check in red and the selected overlay on top
+    fn render_board(&self, frame: &mut Frame, chunk: Rect, layout: ScreenLayout) {
+        let mut lines = board_lines(|square| self.board.get(square), self.highlighted, layout);
+        for &shape in self.shapes() {
+            self.paint_shape(&mut lines, shape, layout);
+        }
+        let mut paint = |square: Square, color: Color| {
+            for span in square_spans(&mut lines, square, layout) {
+                span.style.bg = Some(color);
             }
-            // TODO: This should render the board using ascii art instead of unicode chars
-            ScreenLayout::Large => todo!(),
-        }
-    }
-
-    fn render_small_board(&self, frame: &mut Frame, chunk: Rect) {
-        let mut para = Vec::new();
-        for i in 0..8 {
-            let mut curr = Vec::new();
-            for j in 0..8 {
-                let piece = self.at(i, j);
-                let mut to_push = piece.map_or(' ', |x| x.piece_type.to_unicode()).to_string();
-                to_push.push(' ');
-                curr.push(Span::styled(
-                    to_push,
-                    Style::new()
-                        .bg(
-                            #[allow(clippy::collapsible_else_if)]
-                            if (i + j) & 1 == 0 {
-                                if self.highlighted.is_some_and(|x| {
-                                    usize::from(x.row) == i && usize::from(x.col) == j
-                                }) {
-                                    Color::LightBlue
-                                } else {
-                                    Color::Blue
-                                }
-                            } else {
-                                if self.highlighted.is_some_and(|x| {
-                                    usize::from(x.row) == i && usize::from(x.col) == j
-                                }) {
-                                    Color::LightGreen
-                                } else {
-                                    Color::Green
-                                }
-                            },
-                        )
-                        .fg(piece.map_or(Color::Green, |x| x.color.color())),
-                ));
+        };
+        let turn = self.board.turn;
+        if let Some(king) = self.board.king(turn).filter(|_| self.in_check(turn)) {
+            paint(king, Color::Red);
+        }
+        match self.overlay {
+            Overlay::None => (),
+            Overlay::Hanging => {
+                for square in [super::Color::White, super::Color::Black]
+                    .into_iter()
+                    .flat_map(|color| self.hanging(color))
+                {
+                    paint(square, Color::Magenta);
+                }
+            }
+            Overlay::Control => {
+                for square in (0..64).map(Square::from_index) {
+                    let white = self.board.attackers(square, super::Color::White).count();
+                    let black = self.board.attackers(square, super::Color::Black).count();
+                    let (count, color) = match white.cmp(&black) {
+                        Ordering::Equal => continue,
+                        Ordering::Greater => (white - black, super::Color::White),
+                        Ordering::Less => (black - white, super::Color::Black),
+                    };
+                    // The count goes in the space after the piece, in the top line of the square
+                    let Some(span) = square_spans(&mut lines, square, layout).next() else {
+                        continue;
+                    };
+                    set_last(span, char::from_digit(count.min(9), 10).unwrap_or('9'));
+                    if self.board.get(square).is_none() {
+                        span.style.fg = Some(color.color());
+                    }
+                }
             }
-            para.push(Line::from(curr));
         }
-        frame.render_widget(Paragraph::new(para), chunk);
+        frame.render_widget(Paragraph::new(lines), chunk);
     }
 
-    fn render_moves(&self, frame: &mut Frame, chunk: Rect) {
-        let para = Paragraph::new(
-            self.moves
-                .chunks(2)
-                .map(|arr| {
-                    if arr.len() == 1 {
-                        (arr[0], None)
-                    } else {
-                        (arr[0], Some(arr[1]))
-                    }
-                })
-                .enumerate()
-                .map(|(i, (a, b))| {
-                    Line::from(Span::raw(format!(
-                        "{:4}: {:>7}  {:<7}",
-                        i + 1,
-                        a.get_notation(self).unwrap(),
-                        b.map_or(String::new(), |x| x.get_notation(self).unwrap())
-                    )))
+    /// Colors the background of a square, or draws an arrow as lines over the empty squares
+    /// it crosses with its head on the last one.
+    /// Arrows that aren't straight, like a knight's, only get their head
+    fn paint_shape(&self, lines: &mut [Line<'static>], shape: Shape, layout: ScreenLayout) {
+        let color = shape.color.color();
+        if shape.is_square() {
+            for span in square_spans(lines, shape.from, layout) {
+                span.style.bg = Some(color);
+            }
+            return;
+        }
+        let width = usize::from(layout.square_size().1);
+        let rows =
+            i8::try_from(shape.to.row).unwrap_or(0) - i8::try_from(shape.from.row).unwrap_or(0);
+        let cols =
+            i8::try_from(shape.to.col).unwrap_or(0) - i8::try_from(shape.from.col).unwrap_or(0);
+        let (dr, dc) = (rows.signum(), cols.signum());
+        if rows == 0 || cols == 0 || rows.abs() == cols.abs() {
+            // Lines across go all the way through a square, the others through its middle
+            let line = match (dr, dc) {
+                (0, _) => "─".repeat(width),
+                (_, 0) => format!("{:^width$}", "│"),
+                _ if dr == dc => format!("{:^width$}", "╱"),
+                _ => format!("{:^width$}", "╲"),
+            };
+            for step in 1..rows.abs().max(cols.abs()) {
+                let Some(square) = shape.from.offset(dr * step, dc * step) else {
+                    break;
+                };
+                if self.board.get(square).is_none() {
+                    let span = middle_span(lines, square, layout);
+                    span.content = line.clone().into();
+                    span.style.fg = Some(color);
+                }
+            }
+        }
+        let head = match (dr, dc) {
+            (1, 0) => '↑',
+            (-1, 0) => '↓',
+            (0, 1) => '→',
+            (0, -1) => '←',
+            (1, 1) => '↗',
+            (1, -1) => '↖',
+            (-1, 1) => '↘',
+            _ => '↙',
+        };
+        // The head goes next to a piece, on a square in the color of the arrow
+        if self.board.get(shape.to).is_some() {
+            for span in square_spans(lines, shape.to, layout) {
+                span.style.bg = Some(color);
+            }
+            set_last(middle_span(lines, shape.to, layout), head);
+        } else {
+            let span = middle_span(lines, shape.to, layout);
+            span.content = match layout {
+                ScreenLayout::Small => format!("{head} "),
+                ScreenLayout::Large => format!("{head:^width$}"),
+            }
+            .into();
+            span.style.fg = Some(color);
+        }
+    }
+
+    /// Lets the user pick the piece a pawn promotes to
+    fn render_promotion(&self, frame: &mut Frame, board: Rect) {
+        if self.promotion.is_none() {
+            return;
+        }
+        let area = promotion_area(board).intersection(frame.size());
+        let pieces = PROMOTIONS
+            .iter()
+            .map(|piece| {
+                Span::styled(
+                    format!("{} ", piece.to_unicode()),
+                    Style::new().fg(self.board.turn.color()).bg(Color::Gray),
+                )
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(Line::from(pieces))
+                .block(Block::new().borders(Borders::ALL).title("q r b n")),
+            area,
+        );
+    }
+
+    /// Shows the pieces each side captured, followed by its lead in points
+    fn render_material(&self, frame: &mut Frame, chunk: Rect) {
+        let difference = self.material().difference();
+        let lines = [super::Color::White, super::Color::Black]
+            .into_iter()
+            .map(|color| {
+                let taken = color.opposite();
+                let pieces = self
+                    .captured(taken)
+                    .into_iter()
+                    .map(PieceType::to_unicode)
+                    .collect::<String>();
+                let lead = match color {
+                    super::Color::White => difference,
+                    super::Color::Black => -difference,
+                };
+                let mut spans = vec![Span::styled(
+                    pieces,
+                    Style::new().fg(taken.color()).bg(Color::DarkGray),
+                )];
+                if lead > 0 {
+                    spans.push(Span::raw(format!(" +{lead}")));
+                }
+                Line::from(spans)
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(Paragraph::new(lines), chunk);
+    }
+
+    /// How many lines [`Position::render_variant`] needs
+    const fn variant_lines(&self) -> u16 {
+        match self.board.variant {
+            Variant::Crazyhouse => 2,
+            Variant::ThreeCheck => 1,
+            _ => 0,
+        }
+    }
+
+    /// Shows the crazyhouse pockets or the checks given in three-check under the board
+    fn render_variant(&self, frame: &mut Frame, chunk: Rect) {
+        let style = |color: super::Color| Style::new().fg(color.color()).bg(Color::DarkGray);
+        let sides = [super::Color::White, super::Color::Black];
+        let lines = match self.board.variant {
+            Variant::Crazyhouse => sides
+                .into_iter()
+                .map(|color| {
+                    Line::from(
+                        POCKET_PIECES
+                            .into_iter()
+                            .filter_map(|piece_type| {
+                                let count = self.board.pocket(color, piece_type);
+                                (count > 0).then(|| {
+                                    Span::styled(
+                                        format!("{}{count} ", piece_type.to_unicode()),
+                                        style(color),
+                                    )
+                                })
+                            })
+                            .collect::<Vec<_>>(),
+                    )
                 })
-                .collect::<Vec<_>>(),
+                .collect(),
+            Variant::ThreeCheck => vec![Line::from(
+                std::iter::once(Span::raw("checks "))
+                    .chain(sides.into_iter().map(|color| {
+                        Span::styled(format!("+{}", self.board.checks(color)), style(color))
+                    }))
+                    .collect::<Vec<_>>(),
+            )],
+            _ => Vec::new(),
+        };
+        frame.render_widget(Paragraph::new(lines), chunk);
+    }
+
+    /// Shows the result under the board once the game is over,
+    /// whether insert mode is on or a warning that the last move gave away material
+    fn render_status(&self, frame: &mut Frame, chunk: Rect) {
+        let status = self.status();
+        let text = if let Some(result) = status.result() {
+            format!("{result} {status}")
+        } else if self.inserting {
+            "Insert mode: moves go in front of the continuation".to_string()
+        } else if let Some((r#move, loss)) = self.losing_move() {
+            format!("{move} loses material ({loss})")
+        } else {
+            return;
+        };
+        frame.render_widget(Paragraph::new(text).wrap(Wrap { trim: true }), chunk);
+    }
+
+    /// The last move in notation and how many points it loses by SEE,
+    /// if it was a capture that loses material
+    fn losing_move(&self) -> Option<(String, i32)> {
+        let r#move = self.last_move()?;
+        let before = &self.tree.state(self.tree.parent(self.cursor)?).board;
+        let captures = match r#move.kind {
+            MoveKind::Normal => before.get(r#move.end).is_some(),
+            MoveKind::EnPassant => true,
+            MoveKind::Castle | MoveKind::Drop(_) => false,
+        };
+        let see = before.see(r#move);
+        (captures && see < 0).then(|| (r#move.notation(before).unwrap_or_default(), see))
+    }
+
+    /// Writes the line following `parent` with its variations in parentheses,
+    /// each move after a space.
+    /// The first move gets its number even when black plays it if `numbered` is set
+    fn variation_text(&self, mut parent: NodeId, mut numbered: bool, line: &mut Vec<MoveText>) {
+        let tree = &self.tree;
+        while let Some((&next, variations)) = tree.children(parent).split_first() {
+            numbered |= self.push_move(next, numbered, " ", line);
+            for &variation in variations {
+                let commented = self.push_move(variation, true, " (", line);
+                self.variation_text(variation, commented, line);
+                line.push(MoveText::plain(")"));
+                numbered = true;
+            }
+            parent = next;
+        }
+    }
+
+    /// Writes a move with its comments, after `prefix`.
+    /// Returns whether a comment came after it, which means the next move needs its number
+    fn push_move(
+        &self,
+        id: NodeId,
+        numbered: bool,
+        prefix: &str,
+        line: &mut Vec<MoveText>,
+    ) -> bool {
+        let node = self.tree.node(id);
+        let mut prefix = prefix.to_string();
+        if !node.comment_before().is_empty() {
+            line.push(MoveText::plain(prefix.trim_end()));
+            push_comment(node.comment_before(), line);
+            prefix = " ".to_string();
+        }
+        let numbered = numbered || !node.comment_before().is_empty();
+        line.push(MoveText::r#move(
+            format!("{prefix}{}", self.numbered_move(id, numbered)),
+            id,
+        ));
+        push_comment(node.comment(), line);
+        !node.comment().is_empty()
+    }
+
+    /// A move with its number in front if white played it or `numbered` is set
+    fn numbered_move(&self, id: NodeId, numbered: bool) -> String {
+        let (number, white) = self.tree.move_number(id);
+        let san = self.annotated_san(id);
+        match (white, numbered) {
+            (true, _) => format!("{number}. {san}"),
+            (false, true) => format!("{number}... {san}"),
+            (false, false) => san,
+        }
+    }
+
+    /// A move in SAN with its glyphs. Glyphs like `!` go right after the move,
+    /// the others follow after a space
+    fn annotated_san(&self, id: NodeId) -> String {
+        let mut san = self.tree.san(id).unwrap_or_default().to_string();
+        for nag in self.tree.node(id).nags() {
+            if nag.is_move_assessment() {
+                san = format!("{san}{nag}");
+            } else {
+                san = format!("{san} {nag}");
+            }
+        }
+        san
+    }
+
+    /// The mainline in two columns, with the comments and the variations
+    /// on the lines after the move they belong to
+    fn move_list(&self) -> Vec<Vec<MoveText>> {
+        let tree = &self.tree;
+        let mut lines = Vec::new();
+        let comment_line = |comment: &str| {
+            let mut line = vec![MoveText::plain("     ")];
+            push_comment(comment, &mut line);
+            line
+        };
+        let game_comment = tree.node(GameTree::ROOT).comment();
+        if !game_comment.is_empty() {
+            lines.push(comment_line(game_comment));
+        }
+        // The number and white's move of the row waiting for black's move
+        let mut row: Option<Vec<MoveText>> = None;
+        let mut parent = GameTree::ROOT;
+        while let Some((&next, variations)) = tree.children(parent).split_first() {
+            let (number, white) = tree.move_number(next);
+            let node = tree.node(next);
+            if !node.comment_before().is_empty() {
+                lines.extend(row.take());
+                lines.push(comment_line(node.comment_before()));
+            }
+            let san = self.annotated_san(next);
+            // The longest possible move I would have to format is 999. Nb8xc6+ Ne5xc6+
+            // NOTE: Doesn't allow for more than 1000 moves natively
+            let line = row.get_or_insert_with(|| {
+                vec![
+                    MoveText::plain(format!("{number:4}: ")),
+                    MoveText::plain(format!("{:>7}", "...")),
+                ]
+            });
+            if white {
+                line[1] = MoveText::r#move(format!("{san:>7}"), next);
+            } else {
+                line.push(MoveText::plain("  "));
+                line.push(MoveText::r#move(format!("{san:<7}"), next));
+            }
+            if !white || !variations.is_empty() || !node.comment().is_empty() {
+                lines.extend(row.take());
+            }
+            if !node.comment().is_empty() {
+                lines.push(comment_line(node.comment()));
+            }
+            for &variation in variations {
+                let mut line = Vec::new();
+                let commented = self.push_move(variation, true, "      (", &mut line);
+                self.variation_text(variation, commented, &mut line);
+                line.push(MoveText::plain(")"));
+                lines.push(line);
+            }
+            parent = next;
+        }
+        lines.extend(row);
+        lines
+    }
+
+    /// The move list broken into rows that fit `width`.
+    /// Long variations continue on indented rows
+    fn move_rows(&self, width: u16) -> Vec<Vec<MoveText>> {
+        let width = usize::from(width);
+        let mut rows = Vec::new();
+        for line in self.move_list() {
+            let mut row = Vec::new();
+            let mut used = 0;
+            for text in line {
+                let len = text.text.chars().count();
+                if used + len > width && used > MOVES_INDENT.len() {
+                    rows.push(std::mem::take(&mut row));
+                    row.push(MoveText::plain(MOVES_INDENT));
+                    used = MOVES_INDENT.len();
+                }
+                used += len;
+                row.push(text);
+            }
+            rows.push(row);
+        }
+        rows
+    }
+
+    /// The first row shown so the move at the cursor is visible,
+    /// about halfway down once the list is longer than the area
+    fn moves_scroll(&self, rows: &[Vec<MoveText>], height: u16) -> usize {
+        let height = usize::from(height);
+        let current = rows
+            .iter()
+            .position(|row| row.iter().any(|x| x.node == Some(self.cursor)))
+            .unwrap_or(0);
+        current
+            .saturating_sub(height / 2)
+            .min(rows.len().saturating_sub(height))
+    }
+
+    /// Shows the moves, scrolled so the current one is visible and highlighted,
+    /// and remembers where each move went for [`Position::node_at`]
+    fn render_moves(&self, frame: &mut Frame, chunk: Rect) {
+        let rows = self.move_rows(chunk.width);
+        let scroll = self.moves_scroll(&rows, chunk.height);
+        let mut spots = self.move_spots.borrow_mut();
+        spots.clear();
+        let lines = rows
+            .into_iter()
+            .skip(scroll)
+            .take(usize::from(chunk.height))
+            .zip(chunk.y..)
+            .map(|(row, y)| {
+                let mut x = chunk.x;
+                Line::from(
+                    row.into_iter()
+                        .map(|text| {
+                            let width =
+                                u16::try_from(text.text.chars().count()).unwrap_or(u16::MAX);
+                            if let Some(node) = text.node {
+                                // Clicking the space in front of a move doesn't select it
+                                let padding =
+                                    text.text.chars().take_while(|&c| c == ' ' || c == '(');
+                                let padding = u16::try_from(padding.count()).unwrap_or(0);
+                                let spot = Rect::new(x + padding, y, width - padding, 1);
+                                spots.push((spot, node));
+                            }
+                            x = x.saturating_add(width);
+                            let style = if text.node == Some(self.cursor) {
+                                Style::new().bg(Color::Gray).fg(Color::Black)
+                            } else if text.comment {
+                                Style::new().fg(Color::Cyan)
+                            } else {
+                                Style::new()
+                            };
+                            Span::styled(text.text, style)
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(Paragraph::new(lines), chunk);
+    }
+
+    /// Shows the comment being edited in a popup at the top of the moves
+    fn render_comment_edit(&self, frame: &mut Frame, moves: Rect) {
+        let Some(edit) = &self.comment_edit else {
+            return;
+        };
+        let area = Rect { height: 3, ..moves }.intersection(frame.size());
+        let title = match (edit.node == GameTree::ROOT, edit.before) {
+            (true, _) => "comment on the game".to_string(),
+            (false, before) => format!(
+                "comment {} {}",
+                if before { "before" } else { "after" },
+                self.numbered_move(edit.node, true)
+            ),
+        };
+        // Scroll sideways so the cursor stays inside the border
+        let chars = edit.text.chars().collect::<Vec<_>>();
+        let start = edit
+            .cursor
+            .saturating_sub(usize::from(area.width.saturating_sub(3)));
+        let text = |range: std::ops::Range<usize>| chars[range].iter().collect::<String>();
+        let cursor = chars.get(edit.cursor).map_or(' ', |&c| c).to_string();
+        let end = (edit.cursor + 1).min(chars.len());
+        let line = Line::from(vec![
+            Span::raw(text(start..edit.cursor)),
+            Span::styled(cursor, Style::new().bg(Color::Gray).fg(Color::Black)),
+            Span::raw(text(end..chars.len())),
+        ]);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(line).block(Block::new().borders(Borders::ALL).title(title)),
+            area,
+        );
+    }
+
+    /// Shows the move being typed in a popup at the top of the moves,
+    /// with why it couldn't be played in the title
+    fn render_move_entry(&self, frame: &mut Frame, moves: Rect) {
+        let Some(entry) = &self.move_entry else {
+            return;
+        };
+        let area = Rect { height: 3, ..moves }.intersection(frame.size());
+        let title = entry
+            .error
+            .as_deref()
+            .unwrap_or("move (Enter plays it, Esc cancels)");
+        let line = Line::from(vec![
+            Span::raw(entry.text.as_str()),
+            Span::styled(" ", Style::new().bg(Color::Gray)),
+        ]);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(line).block(Block::new().borders(Borders::ALL).title(title)),
+            area,
         );
-        frame.render_widget(para, chunk);
+    }
+
+    /// The move drawn at a point on the screen the last time the moves were shown,
+    /// if there is one
+    pub(super) fn node_at(&self, column: u16, row: u16) -> Option<NodeId> {
+        self.move_spots
+            .borrow()
+            .iter()
+            .find(|(spot, _)| {
+                (spot.x..spot.x + spot.width).contains(&column)
+                    && (spot.y..spot.y + spot.height).contains(&row)
+            })
+            .map(|&(_, node)| node)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use crate::position::{Position, ScreenLayout, Shape, ShapeColor, Square};
+
+    #[test]
+    fn large_board() {
+        let mut pos = Position::default();
+        let (e2, e4, d5) = (Square::new(1, 4), Square::new(3, 4), Square::new(4, 3));
+        pos.toggle_shape(Shape {
+            color: ShapeColor::Green,
+            from: e2,
+            to: e4,
+        });
+        pos.toggle_shape(Shape::square(ShapeColor::Red, d5));
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        terminal
+            .draw(|frame| pos.draw(frame, frame.size(), ScreenLayout::Large))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        // A square is seven columns wide and three lines high, from the eighth rank down
+        let middle = |square: Square| {
+            let x = u16::from(square.col) * 7 + 3;
+            let y = u16::from(7 - square.row) * 3 + 1;
+            buffer.get(x, y).clone()
+        };
+        assert_eq!(middle(Square::new(2, 4)).symbol(), "│");
+        assert_eq!(middle(e4).symbol(), "↑");
+        assert_eq!(middle(e4).fg, ShapeColor::Green.color());
+        assert_eq!(middle(d5).bg, ShapeColor::Red.color());
+        assert_eq!(middle(Square::new(0, 4)).symbol(), "_");
     }
 }
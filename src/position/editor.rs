@@ -0,0 +1,466 @@
+//! The setup screen, where a position is built piece by piece before playing from it
+
+use crossterm::event::{KeyCode, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color as TermColor, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+use super::{
+    board::Board,
+    draw::board_lines,
+    fen::{write_checks, write_placement},
+    moves::Square,
+    Color, FenError, Piece, PieceType, Position, ScreenLayout,
+};
+
+/// Positions the editor can load with a single key
+pub const PRESETS: [(&str, &str); 7] = [
+    (
+        "Start position",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    (
+        "King and queen against king",
+        "8/8/8/4k3/8/8/8/3QK3 w - - 0 1",
+    ),
+    (
+        "King and rook against king",
+        "8/8/8/4k3/8/8/8/R3K3 w - - 0 1",
+    ),
+    ("Bishop and knight mate", "8/8/8/4k3/8/8/8/2B1KN2 w - - 0 1"),
+    (
+        "King and pawn against king",
+        "8/8/8/4k3/8/8/4P3/4K3 w - - 0 1",
+    ),
+    ("Lucena position", "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1"),
+    ("Philidor position", "4k3/8/r7/4PK2/8/8/8/1R6 b - - 0 1"),
+];
+
+/// The pieces in the palette under the board, in the order they are shown
+const PALETTE: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+/// The castling rights in the order the keys 1 to 4 toggle them
+const CASTLING: [char; 4] = ['K', 'Q', 'k', 'q'];
+
+/// A position being set up.
+/// It doesn't have to be legal while editing, [`Editor::position`] says what is wrong with it
+#[derive(Debug, Clone)]
+pub struct Editor {
+    /// The pieces and side to move. The variant, crazyhouse pockets and three-check counters
+    /// are kept as they were
+    board: Board,
+    /// Whether `KQkq` are set, in that order
+    castling: [bool; 4],
+    en_passant: Option<Square>,
+    halfmove_count: u16,
+    movecount: u64,
+    /// The square the keyboard works on
+    cursor: Square,
+    /// The piece placed by clicking the board, or `None` to remove pieces
+    tool: Option<Piece>,
+    /// The preset loaded last, in [`PRESETS`]
+    preset: usize,
+    /// Whether the game is Chess960, which a setup that looks normal doesn't show
+    chess960: bool,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::from(&Position::default())
+    }
+}
+
+impl From<&Position> for Editor {
+    /// Starts editing the current position of a game
+    fn from(position: &Position) -> Self {
+        let (white_kingside, white_queenside) = position.board.castling_rights.get(Color::White);
+        let (black_kingside, black_queenside) = position.board.castling_rights.get(Color::Black);
+        Self {
+            board: position.board,
+            castling: [
+                white_kingside,
+                white_queenside,
+                black_kingside,
+                black_queenside,
+            ]
+            .map(|x| x.is_some()),
+            en_passant: position.board.en_passant,
+            halfmove_count: position.halfmove_count,
+            movecount: position.movecount,
+            cursor: Square::new(0, 4),
+            tool: Some(Piece::new(PieceType::Pawn, Color::White)),
+            preset: 0,
+            chess960: position.is_chess960(),
+        }
+    }
+}
+
+impl Editor {
+    /// The position as a fen, whether it is valid or not
+    #[must_use]
+    pub fn to_fen(&self) -> String {
+        let castling = CASTLING
+            .iter()
+            .zip(self.castling)
+            .filter_map(|(&c, set)| set.then_some(c))
+            .collect::<String>();
+        let mut en_passant = self
+            .en_passant
+            .map_or_else(|| "-".to_string(), |x| x.to_string());
+        if let Some(checks) = write_checks(&self.board) {
+            en_passant = format!("{en_passant} {checks}");
+        }
+        format!(
+            "{} {} {} {en_passant} {} {}",
+            write_placement(&self.board),
+            match self.board.turn {
+                Color::White => 'w',
+                Color::Black => 'b',
+            },
+            if castling.is_empty() { "-" } else { &castling },
+            self.halfmove_count,
+            self.movecount
+        )
+    }
+
+    /// The position that was set up
+    /// # Errors
+    /// Returns what makes the position invalid, like a missing king
+    pub fn position(&self) -> Result<Position, FenError> {
+        let mut position = Position::try_from_variant(self.to_fen(), self.board.variant)?;
+        position.chess960 |= self.chess960;
+        Ok(position)
+    }
+
+    /// Removes every piece and right, keeping the side to move and counters
+    pub fn clear(&mut self) {
+        for index in 0..64 {
+            self.set(Square::from_index(index), None);
+        }
+        self.castling = [false; 4];
+        self.en_passant = None;
+    }
+
+    /// Loads a position from [`PRESETS`], in the variant that is being edited
+    pub fn load_preset(&mut self, preset: usize) {
+        let Some((_, fen)) = PRESETS.get(preset) else {
+            return;
+        };
+        if let Ok(position) = Position::try_from_variant(*fen, self.board.variant) {
+            *self = Self {
+                cursor: self.cursor,
+                tool: self.tool,
+                preset,
+                chess960: self.chess960,
+                ..Self::from(&position)
+            };
+        }
+    }
+
+    /// Places a piece on a square, or clears it.
+    /// An en passant square that is now occupied is removed
+    fn set(&mut self, square: Square, piece: Option<Piece>) {
+        self.board.set(square, piece);
+        let promoted = self.board.promoted() & !super::bitboard::Bitboard::from_square(square);
+        self.board.set_promoted(promoted);
+        if self.en_passant == Some(square) {
+            self.en_passant = None;
+        }
+    }
+
+    /// Handles keyboard events. Enter and Esc are left to the caller
+    /// # Return values
+    /// returns true if the key was used up
+    pub fn handle_keyboard(&mut self, code: KeyCode) -> bool {
+        let Square { row, col } = self.cursor;
+        match code {
+            KeyCode::Up => self.cursor = Square::new((row + 1).min(7), col),
+            KeyCode::Down => self.cursor = Square::new(row.saturating_sub(1), col),
+            KeyCode::Right => self.cursor = Square::new(row, (col + 1).min(7)),
+            KeyCode::Left => self.cursor = Square::new(row, col.saturating_sub(1)),
+            KeyCode::Delete | KeyCode::Backspace | KeyCode::Char('x') => {
+                self.set(self.cursor, None);
+            }
+            KeyCode::Char('t') => self.board.turn = self.board.turn.opposite(),
+            KeyCode::Char(c @ '1'..='4') => {
+                let index = usize::from(c as u8 - b'1');
+                self.castling[index] = !self.castling[index];
+            }
+            KeyCode::Char('e') => {
+                self.en_passant = (self.en_passant != Some(self.cursor)).then_some(self.cursor);
+            }
+            KeyCode::Char('<') => self.halfmove_count = self.halfmove_count.saturating_sub(1),
+            KeyCode::Char('>') => self.halfmove_count = self.halfmove_count.saturating_add(1),
+            KeyCode::Char('-') => self.movecount = self.movecount.saturating_sub(1).max(1),
+            KeyCode::Char('+') => self.movecount += 1,
+            KeyCode::Char('c') => self.clear(),
+            KeyCode::Char('s') => self.load_preset(0),
+            KeyCode::Char('l') => self.load_preset((self.preset + 1) % PRESETS.len()),
+            KeyCode::Char(c) => {
+                let Some(piece) = Piece::from(c) else {
+                    return false;
+                };
+                self.tool = Some(piece);
+                self.set(self.cursor, Some(piece));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handles clicks on the setup screen.
+    /// A left click on the board places the selected piece, or removes it if it is already
+    /// there, and a right click removes whatever is on the square.
+    /// Clicking the palette under the board selects the piece to place
+    pub fn handle_mouse(&mut self, rect: &Rect, mouse: MouseEvent) {
+        let right = match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => false,
+            MouseEventKind::Down(MouseButton::Right) => true,
+            _ => return,
+        };
+        // NOTE: Like the game, this assumes that the board is located top left
+        let (Some(row), Some(column)) = (
+            mouse.row.checked_sub(rect.y),
+            mouse.column.checked_sub(rect.x),
+        ) else {
+            return;
+        };
+        let Ok(column) = u8::try_from(column / 2) else {
+            return;
+        };
+        match row {
+            0..=7 if column < 8 => {
+                let square = Square::new(7 - u8::try_from(row).unwrap_or_default(), column);
+                self.cursor = square;
+                let piece = if right || self.board.get(square) == self.tool {
+                    None
+                } else {
+                    self.tool
+                };
+                self.set(square, piece);
+            }
+            8 | 9 if !right => {
+                let color = if row == 8 { Color::White } else { Color::Black };
+                match PALETTE.get(usize::from(column)) {
+                    Some(&piece_type) => self.tool = Some(Piece::new(piece_type, color)),
+                    None if usize::from(column) == PALETTE.len() => self.tool = None,
+                    None => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Draws the setup screen: the board with the palette under it, the rest of the
+    /// position and whether it is valid next to it, and the fen at the bottom
+    pub fn draw(&self, frame: &mut Frame, chunk: Rect) {
+        let vertical = Layout::vertical([Constraint::Min(10), Constraint::Length(1)]).split(chunk);
+        let chunks = Layout::horizontal([
+            Constraint::Length(16),
+            Constraint::Max(2),
+            Constraint::Min(20),
+        ])
+        .split(vertical[0]);
+        let left =
+            Layout::vertical([Constraint::Length(8), Constraint::Length(2)]).split(chunks[0]);
+        frame.render_widget(
+            Paragraph::new(board_lines(
+                |x| self.board.get(x),
+                Some(self.cursor),
+                ScreenLayout::Small,
+            )),
+            left[0],
+        );
+        frame.render_widget(Paragraph::new(self.palette()), left[1]);
+        frame.render_widget(Paragraph::new(self.info()), chunks[2]);
+        frame.render_widget(Line::from(Span::raw(self.to_fen())), vertical[1]);
+    }
+
+    /// One line of pieces per color and an eraser, with the selected tool highlighted
+    fn palette(&self) -> Vec<Line<'static>> {
+        let selected = |tool| {
+            let bg = if self.tool == tool {
+                TermColor::Gray
+            } else {
+                TermColor::DarkGray
+            };
+            Style::new().bg(bg)
+        };
+        [Color::White, Color::Black]
+            .into_iter()
+            .map(|color| {
+                let mut spans = PALETTE
+                    .into_iter()
+                    .map(|piece_type| {
+                        let piece = Piece::new(piece_type, color);
+                        Span::styled(
+                            format!("{} ", piece_type.to_unicode()),
+                            selected(Some(piece)).fg(color.color()),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                spans.push(Span::styled("x ", selected(None).fg(TermColor::Red)));
+                Line::from(spans)
+            })
+            .collect()
+    }
+
+    /// The side to move, rights and counters with the keys that change them,
+    /// followed by the result of validating the position
+    fn info(&self) -> Vec<Line<'static>> {
+        let castling = CASTLING
+            .iter()
+            .zip(self.castling)
+            .map(|(&c, set)| if set { c } else { '-' })
+            .collect::<String>();
+        let mut lines = [
+            format!("side to move: {:?} (t)", self.board.turn),
+            format!("castling: {castling} (1-4)"),
+            format!(
+                "en passant: {} (e)",
+                self.en_passant
+                    .map_or_else(|| "-".to_string(), |x| x.to_string())
+            ),
+            format!("halfmove clock: {} (< >)", self.halfmove_count),
+            format!("fullmove number: {} (- +)", self.movecount),
+            format!("preset: {} (l, s, c)", PRESETS[self.preset].0),
+            "place: KQRBNP kqrbnp, remove: x".to_string(),
+            "enter: play from here, esc: cancel".to_string(),
+            String::new(),
+        ]
+        .map(|x| Line::from(Span::raw(x)))
+        .to_vec();
+        lines.push(match self.position() {
+            Ok(_) => Line::from(Span::styled("valid", Style::new().fg(TermColor::Green))),
+            Err(err) => Line::from(Span::styled(
+                err.to_string(),
+                Style::new().fg(TermColor::Red),
+            )),
+        });
+        lines
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+    use ratatui::layout::Rect;
+
+    use super::{Editor, PRESETS};
+    use crate::position::{FenError, Position, Variant};
+
+    fn click(editor: &mut Editor, button: MouseButton, column: u16, row: u16) {
+        let event = MouseEvent {
+            kind: MouseEventKind::Down(button),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+        editor.handle_mouse(&Rect::new(0, 0, 80, 24), event);
+    }
+
+    #[test]
+    fn round_trip() {
+        let fen = "r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 3 20";
+        let editor = Editor::from(&Position::try_from(fen).unwrap());
+        assert_eq!(editor.to_fen(), fen);
+        assert_eq!(editor.position().unwrap().to_fen(), fen);
+        assert_eq!(Editor::default().to_fen(), PRESETS[0].1);
+        // The variant's own fields stay the same
+        for (fen, variant) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 1+2 0 1",
+                Variant::ThreeCheck,
+            ),
+            ("4k3/8/8/8/8/8/8/4K3[QNp] b - - 0 1", Variant::Crazyhouse),
+        ] {
+            let editor = Editor::from(&Position::try_from_variant(fen, variant).unwrap());
+            assert_eq!(editor.to_fen(), fen);
+            assert_eq!(editor.position().unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn keyboard() {
+        let mut editor = Editor::default();
+        editor.handle_keyboard(KeyCode::Char('c'));
+        assert!(matches!(
+            editor.position(),
+            Err(FenError::InvalidKingCount { .. })
+        ));
+        // The cursor starts on e1
+        editor.handle_keyboard(KeyCode::Char('K'));
+        for _ in 0..7 {
+            editor.handle_keyboard(KeyCode::Up);
+        }
+        editor.handle_keyboard(KeyCode::Char('k'));
+        editor.handle_keyboard(KeyCode::Down);
+        editor.handle_keyboard(KeyCode::Char('Q'));
+        // Black would be in check with white to move
+        assert!(matches!(
+            editor.position(),
+            Err(FenError::OpponentInCheck { .. })
+        ));
+        editor.handle_keyboard(KeyCode::Char('t'));
+        assert_eq!(editor.to_fen(), "4k3/4Q3/8/8/8/8/8/4K3 b - - 0 1");
+        editor.handle_keyboard(KeyCode::Char('x'));
+        editor.handle_keyboard(KeyCode::Char('+'));
+        editor.handle_keyboard(KeyCode::Char('>'));
+        editor.handle_keyboard(KeyCode::Char('1'));
+        assert_eq!(editor.to_fen(), "4k3/8/8/8/8/8/8/4K3 b K - 1 2");
+        assert!(matches!(
+            editor.position(),
+            Err(FenError::InconsistentCastling { .. })
+        ));
+        assert!(!editor.handle_keyboard(KeyCode::Char('z')));
+    }
+
+    #[test]
+    fn mouse() {
+        let mut editor = Editor::default();
+        // Pick the black knight from the palette and place it on e4
+        click(&mut editor, MouseButton::Left, 8, 9);
+        click(&mut editor, MouseButton::Left, 8, 4);
+        // Clicking the same piece again removes it
+        click(&mut editor, MouseButton::Left, 8, 3);
+        click(&mut editor, MouseButton::Left, 8, 3);
+        // Right clicks remove pieces, here the pawn on a2
+        click(&mut editor, MouseButton::Right, 0, 6);
+        assert_eq!(
+            editor.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4n3/8/1PPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        // The eraser is after the pieces
+        click(&mut editor, MouseButton::Left, 12, 8);
+        click(&mut editor, MouseButton::Left, 8, 4);
+        assert_eq!(
+            editor.position().unwrap().to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/1PPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+    }
+
+    #[test]
+    fn presets() {
+        let mut editor = Editor::default();
+        for (preset, (name, fen)) in PRESETS.iter().enumerate() {
+            editor.load_preset(preset);
+            assert_eq!(editor.to_fen(), *fen);
+            assert!(editor.position().is_ok(), "{name}");
+        }
+        // Presets keep the variant
+        let mut editor = Editor::from(&Position::new(Variant::Crazyhouse));
+        editor.load_preset(1);
+        assert_eq!(editor.position().unwrap().variant(), Variant::Crazyhouse);
+    }
+}
@@ -0,0 +1,769 @@
+//! Parses fens strictly and reports what is wrong with the ones that don't parse
+
+use std::{error::Error, fmt};
+
+use super::{
+    attacks::Overlay,
+    bitboard::Bitboard,
+    board::{Board, POCKET_PIECES},
+    history::History,
+    moves::Square,
+    tree::GameTree,
+    variant::Variant,
+    CastingRights, Color, Piece, PieceType, Position, State,
+};
+
+/// The six space separated fields of a fen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenField {
+    /// The pieces, rank by rank from the eighth
+    Placement,
+    /// `w` or `b`
+    SideToMove,
+    /// `KQkq`, `-`, or the X-FEN and Shredder-FEN forms with rook files
+    Castling,
+    /// The square behind a pawn that just moved two squares, or `-`
+    EnPassant,
+    /// Three-check only: the checks each side still needs to win, like `3+3`
+    Checks,
+    /// Half moves since the last capture or pawn move
+    HalfmoveClock,
+    /// The number of the full move, starting at 1
+    FullmoveNumber,
+}
+
+impl fmt::Display for FenField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Placement => "piece placement",
+            Self::SideToMove => "side to move",
+            Self::Castling => "castling rights",
+            Self::EnPassant => "en passant square",
+            Self::Checks => "remaining checks",
+            Self::HalfmoveClock => "halfmove clock",
+            Self::FullmoveNumber => "fullmove number",
+        })
+    }
+}
+
+/// Describes why a fen couldn't be parsed.
+/// Every offset is in bytes from the start of the fen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// The fen ended before this field
+    MissingField {
+        /// The first missing field
+        field: FenField,
+        /// The length of the fen
+        offset: usize,
+    },
+    /// There is more text after the fullmove number
+    TrailingData {
+        /// Where the extra text starts
+        offset: usize,
+    },
+    /// A character in the placement isn't a piece or a digit from 1 to 8
+    InvalidPiece {
+        /// The character that isn't a piece
+        found: char,
+        /// Where it is
+        offset: usize,
+    },
+    /// A rank doesn't describe exactly 8 squares
+    InvalidRankLength {
+        /// The rank, where 8 is the first one in the fen
+        rank: u8,
+        /// Where the rank starts
+        offset: usize,
+    },
+    /// The placement doesn't have exactly 8 ranks
+    InvalidRankCount {
+        /// The number of ranks in the placement
+        found: usize,
+        /// Where the placement starts
+        offset: usize,
+    },
+    /// A side doesn't have exactly one king
+    InvalidKingCount {
+        /// The side with the wrong number of kings
+        white: bool,
+        /// The number of kings it has
+        found: u32,
+        /// Where the placement starts
+        offset: usize,
+    },
+    /// There is a pawn on the first or eighth rank
+    PawnOnBackRank {
+        /// Where the pawn is
+        offset: usize,
+    },
+    /// The crazyhouse pocket after the pieces isn't closed or holds something that isn't a piece
+    InvalidPocket {
+        /// Where the problem is
+        offset: usize,
+    },
+    /// The side to move isn't `w` or `b`
+    InvalidSideToMove {
+        /// Where the field starts
+        offset: usize,
+    },
+    /// The side that isn't to move is in check
+    OpponentInCheck {
+        /// Where the side to move field starts
+        offset: usize,
+    },
+    /// A character in the castling field isn't a castling right
+    InvalidCastling {
+        /// The character that isn't a right
+        found: char,
+        /// Where it is
+        offset: usize,
+    },
+    /// A castling right is given twice or without the king and rook in place
+    InconsistentCastling {
+        /// The character of the right
+        found: char,
+        /// Where it is
+        offset: usize,
+    },
+    /// The en passant field isn't `-` or a square a pawn could just have skipped
+    InvalidEnPassant {
+        /// Where the field starts
+        offset: usize,
+    },
+    /// A move counter isn't a number in the allowed range
+    InvalidNumber {
+        /// The counter that is wrong
+        field: FenField,
+        /// Where it starts
+        offset: usize,
+    },
+}
+
+impl FenError {
+    /// The field the error was found in
+    #[must_use]
+    pub const fn field(&self) -> FenField {
+        match self {
+            Self::MissingField { field, .. } | Self::InvalidNumber { field, .. } => *field,
+            Self::TrailingData { .. } => FenField::FullmoveNumber,
+            Self::InvalidPiece { .. }
+            | Self::InvalidRankLength { .. }
+            | Self::InvalidRankCount { .. }
+            | Self::InvalidKingCount { .. }
+            | Self::PawnOnBackRank { .. }
+            | Self::InvalidPocket { .. } => FenField::Placement,
+            Self::InvalidSideToMove { .. } | Self::OpponentInCheck { .. } => FenField::SideToMove,
+            Self::InvalidCastling { .. } | Self::InconsistentCastling { .. } => FenField::Castling,
+            Self::InvalidEnPassant { .. } => FenField::EnPassant,
+        }
+    }
+
+    /// The byte offset in the fen where the error was found
+    #[must_use]
+    pub const fn offset(&self) -> usize {
+        match self {
+            Self::MissingField { offset, .. }
+            | Self::TrailingData { offset }
+            | Self::InvalidPiece { offset, .. }
+            | Self::InvalidRankLength { offset, .. }
+            | Self::InvalidRankCount { offset, .. }
+            | Self::InvalidKingCount { offset, .. }
+            | Self::PawnOnBackRank { offset }
+            | Self::InvalidPocket { offset }
+            | Self::InvalidSideToMove { offset }
+            | Self::OpponentInCheck { offset }
+            | Self::InvalidCastling { offset, .. }
+            | Self::InconsistentCastling { offset, .. }
+            | Self::InvalidEnPassant { offset }
+            | Self::InvalidNumber { offset, .. } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingField { .. } => write!(f, "missing field")?,
+            Self::TrailingData { .. } => write!(f, "unexpected text after the fen")?,
+            Self::InvalidPiece { found, .. } => write!(f, "'{found}' isn't a piece")?,
+            Self::InvalidRankLength { rank, .. } => {
+                write!(f, "rank {rank} doesn't have 8 squares")?;
+            }
+            Self::InvalidRankCount { found, .. } => write!(f, "expected 8 ranks, found {found}")?,
+            Self::InvalidKingCount { white, found, .. } => write!(
+                f,
+                "{} has {found} kings",
+                if *white { "white" } else { "black" }
+            )?,
+            Self::PawnOnBackRank { .. } => write!(f, "pawn on the first or last rank")?,
+            Self::InvalidPocket { .. } => write!(f, "invalid pocket")?,
+            Self::InvalidSideToMove { .. } => write!(f, "expected 'w' or 'b'")?,
+            Self::OpponentInCheck { .. } => write!(f, "the side not to move is in check")?,
+            Self::InvalidCastling { found, .. } => write!(f, "'{found}' isn't a castling right")?,
+            Self::InconsistentCastling { found, .. } => write!(
+                f,
+                "castling right '{found}' is repeated or the king or rook isn't in place"
+            )?,
+            Self::InvalidEnPassant { .. } => write!(f, "no pawn could be taken en passant")?,
+            Self::InvalidNumber { .. } => write!(f, "invalid number")?,
+        }
+        write!(f, " in the {} at offset {}", self.field(), self.offset())
+    }
+}
+
+impl Error for FenError {}
+
+/// Splits the fen on any whitespace and keeps the offset of each field
+fn fields(fen: &str) -> impl Iterator<Item = (usize, &str)> {
+    fen.split_ascii_whitespace()
+        .map(move |field| (field.as_ptr() as usize - fen.as_ptr() as usize, field))
+}
+
+impl Position {
+    /// Parses a fen and create a `Position` from it
+    /// # Errors
+    /// Returns the first problem found in the fen
+    pub fn try_from<T>(fen: T) -> Result<Self, FenError>
+    where
+        T: Into<String>,
+    {
+        Self::try_from_variant(fen, Variant::Standard)
+    }
+
+    /// Parses a fen of a variant. Crazyhouse fens can have a pocket in brackets after the
+    /// pieces and promoted pieces marked with `~`, and three-check fens can have the
+    /// remaining checks after the en passant square
+    /// # Errors
+    /// Returns the first problem found in the fen
+    pub fn try_from_variant<T>(fen: T, variant: Variant) -> Result<Self, FenError>
+    where
+        T: Into<String>,
+    {
+        let fen = fen.into();
+        let mut fields = fields(&fen).collect::<Vec<_>>();
+        let checks = (variant == Variant::ThreeCheck
+            && fields.get(4).is_some_and(|(_, x)| x.contains('+')))
+        .then(|| fields.remove(4));
+        let mut fields = fields.into_iter();
+        let mut next = |field| {
+            fields.next().ok_or(FenError::MissingField {
+                field,
+                offset: fen.len(),
+            })
+        };
+        let placement = next(FenField::Placement)?;
+        let turn = next(FenField::SideToMove)?;
+        let castling = next(FenField::Castling)?;
+        let en_passant = next(FenField::EnPassant)?;
+        let halfmove_field = next(FenField::HalfmoveClock)?;
+        let movecount_field = next(FenField::FullmoveNumber)?;
+        if let Some((offset, _)) = fields.next() {
+            return Err(FenError::TrailingData { offset });
+        }
+
+        let mut board = parse_placement(placement, variant)?;
+        board.turn = match turn.1 {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(FenError::InvalidSideToMove { offset: turn.0 }),
+        };
+        if board.in_check(board.turn.opposite()) {
+            return Err(FenError::OpponentInCheck { offset: turn.0 });
+        }
+        board.castling_rights = parse_castling(&board, castling)?;
+        board.en_passant = parse_en_passant(&board, en_passant)?;
+        if let Some(checks) = checks {
+            parse_checks(&mut board, checks)?;
+        }
+        board.update_key();
+        let halfmove_count =
+            halfmove_field
+                .1
+                .parse::<u16>()
+                .map_err(|_| FenError::InvalidNumber {
+                    field: FenField::HalfmoveClock,
+                    offset: halfmove_field.0,
+                })?;
+        let movecount = movecount_field
+            .1
+            .parse::<u64>()
+            .ok()
+            .filter(|&x| x > 0)
+            .ok_or(FenError::InvalidNumber {
+                field: FenField::FullmoveNumber,
+                offset: movecount_field.0,
+            })?;
+        Ok(Self {
+            starting_position: fen.clone(),
+            board,
+            halfmove_count,
+            movecount,
+            headers: Vec::new(),
+            tree: GameTree::new(State {
+                board,
+                halfmove_count,
+                movecount,
+            }),
+            cursor: GameTree::ROOT,
+            highlighted: None,
+            drawing: None,
+            promotion: None,
+            comment_edit: None,
+            move_entry: None,
+            move_spots: Vec::new().into(),
+            chess960: is_chess960(&board),
+            overlay: Overlay::None,
+            inserting: false,
+            displaced: None,
+            history: History::default(),
+        })
+    }
+}
+
+impl Position {
+    /// Writes the current position as a fen, including the moves made since the start.
+    /// Castling rights use the X-FEN form, which is the same as a normal fen unless
+    /// a Chess960 right can't be written as `K` or `Q`
+    #[must_use]
+    pub fn to_fen(&self) -> String {
+        self.fen(false)
+    }
+
+    /// Writes the current position as a Shredder-FEN, where castling rights are rook files
+    #[must_use]
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen(true)
+    }
+
+    fn fen(&self, shredder: bool) -> String {
+        let placement = write_placement(&self.board);
+        let mut en_passant = self
+            .board
+            .en_passant
+            .map_or_else(|| "-".to_string(), |x| x.to_string());
+        if let Some(checks) = write_checks(&self.board) {
+            en_passant = format!("{en_passant} {checks}");
+        }
+        format!(
+            "{placement} {} {} {en_passant} {} {}",
+            match self.board.turn {
+                Color::White => 'w',
+                Color::Black => 'b',
+            },
+            castling_field(&self.board, shredder),
+            self.halfmove_count,
+            self.movecount
+        )
+    }
+}
+
+/// Writes the three-check field with the checks each side still needs, like `3+3`.
+/// Other variants don't have it
+pub(super) fn write_checks(board: &Board) -> Option<String> {
+    (board.variant == Variant::ThreeCheck).then(|| {
+        let remaining = |color| 3u8.saturating_sub(board.checks(color));
+        format!("{}+{}", remaining(Color::White), remaining(Color::Black))
+    })
+}
+
+/// Writes the piece placement field, with the crazyhouse pocket and promoted markers
+pub(super) fn write_placement(board: &Board) -> String {
+    let crazyhouse = board.variant == Variant::Crazyhouse;
+    let mut placement = String::new();
+    for row in (0..8).rev() {
+        let mut empty = 0;
+        for col in 0..8 {
+            let square = Square::new(row, col);
+            if let Some(piece) = board.get(square) {
+                if empty > 0 {
+                    placement.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                placement.push(piece.to_char());
+                if crazyhouse && board.promoted().contains(square) {
+                    placement.push('~');
+                }
+            } else {
+                empty += 1;
+            }
+        }
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+        if row > 0 {
+            placement.push('/');
+        }
+    }
+    if crazyhouse {
+        placement.push('[');
+        for color in [Color::White, Color::Black] {
+            for piece_type in POCKET_PIECES {
+                let c = Piece::new(piece_type, color).to_char();
+                placement.extend((0..board.pocket(color, piece_type)).map(|_| c));
+            }
+        }
+        placement.push(']');
+    }
+    placement
+}
+
+fn parse_placement(
+    (offset, placement): (usize, &str),
+    variant: Variant,
+) -> Result<Board, FenError> {
+    let mut board = Board::empty();
+    board.variant = variant;
+    let crazyhouse = variant == Variant::Crazyhouse;
+    let placement = match placement.split_once('[') {
+        Some((placement, pocket)) if crazyhouse => {
+            parse_pocket(&mut board, (offset + placement.len() + 1, pocket))?;
+            placement
+        }
+        _ => placement,
+    };
+    let mut ranks = 0;
+    let mut rank_start = offset;
+    for (i, rank) in placement.split('/').enumerate() {
+        ranks += 1;
+        let mut col = 0u8;
+        for (j, c) in rank.char_indices() {
+            let offset = rank_start + j;
+            let row = u8::try_from(7usize.saturating_sub(i)).unwrap_or_default();
+            if let Some(skip @ 1..=8) = c.to_digit(10) {
+                col += u8::try_from(skip).unwrap_or_default();
+            } else if c == '~' && crazyhouse && (1..=8).contains(&col) && i < 8 {
+                // Marks the piece before it as promoted
+                let square = Square::new(row, col - 1);
+                if board.get(square).is_none() {
+                    return Err(FenError::InvalidPiece { found: c, offset });
+                }
+                board.set_promoted(board.promoted() | Bitboard::from_square(square));
+            } else {
+                let piece = Piece::from(c).ok_or(FenError::InvalidPiece { found: c, offset })?;
+                if i < 8 && col < 8 {
+                    // The horde starts with pawns on the first rank
+                    let horde_pawn =
+                        variant == Variant::Horde && piece.color == Color::White && row == 0;
+                    if piece.piece_type == PieceType::Pawn && (row == 0 || row == 7) && !horde_pawn
+                    {
+                        return Err(FenError::PawnOnBackRank { offset });
+                    }
+                    board.set(Square::new(row, col), Some(piece));
+                }
+                col += 1;
+            }
+            if col > 8 {
+                break;
+            }
+        }
+        if col != 8 {
+            return Err(FenError::InvalidRankLength {
+                rank: u8::try_from(8usize.saturating_sub(i)).unwrap_or_default(),
+                offset: rank_start,
+            });
+        }
+        rank_start += rank.len() + 1;
+    }
+    if ranks != 8 {
+        return Err(FenError::InvalidRankCount {
+            found: ranks,
+            offset,
+        });
+    }
+    for (color, white) in [(Color::White, true), (Color::Black, false)] {
+        let found = board.colored(PieceType::King, color).count();
+        if found != 1 && variant.rules().has_royal_king(color) {
+            return Err(FenError::InvalidKingCount {
+                white,
+                found,
+                offset,
+            });
+        }
+    }
+    Ok(board)
+}
+
+/// Parses `KQkq` style rights as well as the rook files used by X-FEN and Shredder-FEN.
+/// `K` and `Q` stand for the outermost rook on that side of the king.
+/// Every right needs the king on its back rank and the rook on the same rank
+fn parse_castling(board: &Board, (offset, raw): (usize, &str)) -> Result<CastingRights, FenError> {
+    let mut rights = CastingRights::default();
+    if raw == "-" {
+        return Ok(rights);
+    }
+    for (i, c) in raw.char_indices() {
+        let offset = offset + i;
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let row = match color {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+        let inconsistent = FenError::InconsistentCastling { found: c, offset };
+        let king = board
+            .colored(PieceType::King, color)
+            .first()
+            .filter(|king| king.row == row);
+        let mut rooks = (0..8).filter(|&col| {
+            board.get(Square::new(row, col)) == Some(Piece::new(PieceType::Rook, color))
+        });
+        let (kingside, rook_col) = match (c.to_ascii_lowercase(), king) {
+            ('k', Some(king)) => (true, rooks.rfind(|&col| col > king.col)),
+            ('q', Some(king)) => (false, rooks.take_while(|&col| col < king.col).next()),
+            // A rook file is on the kingside when it is to the right of the king
+            (file @ 'a'..='h', Some(king)) => {
+                let col = file as u8 - b'a';
+                (col > king.col, rooks.into_iter().find(|&x| x == col))
+            }
+            ('k' | 'q' | 'a'..='h', None) => return Err(inconsistent),
+            _ => return Err(FenError::InvalidCastling { found: c, offset }),
+        };
+        let (has_kingside, has_queenside) = rights.get(color);
+        let repeated = if kingside {
+            has_kingside
+        } else {
+            has_queenside
+        };
+        match rook_col {
+            Some(col) if repeated.is_none() => rights.add(color, kingside, col),
+            _ => return Err(inconsistent),
+        }
+    }
+    Ok(rights)
+}
+
+/// Parses the pieces in a crazyhouse pocket, which has to end with `]`
+fn parse_pocket(board: &mut Board, (offset, pocket): (usize, &str)) -> Result<(), FenError> {
+    let Some(pocket) = pocket.strip_suffix(']') else {
+        return Err(FenError::InvalidPocket {
+            offset: offset + pocket.len(),
+        });
+    };
+    for (i, c) in pocket.char_indices() {
+        let piece = Piece::from(c)
+            .filter(|x| x.piece_type != PieceType::King)
+            .ok_or(FenError::InvalidPocket { offset: offset + i })?;
+        let count = board.pocket(piece.color, piece.piece_type);
+        board.set_pocket(piece.color, piece.piece_type, count.saturating_add(1));
+    }
+    Ok(())
+}
+
+/// Parses the checks white and black still need, like `3+3`
+fn parse_checks(board: &mut Board, (offset, raw): (usize, &str)) -> Result<(), FenError> {
+    let error = FenError::InvalidNumber {
+        field: FenField::Checks,
+        offset,
+    };
+    let (white, black) = raw.split_once('+').ok_or_else(|| error.clone())?;
+    for (color, remaining) in [(Color::White, white), (Color::Black, black)] {
+        let remaining = remaining
+            .parse::<u8>()
+            .ok()
+            .filter(|&x| x <= 3)
+            .ok_or_else(|| error.clone())?;
+        board.set_checks(color, 3 - remaining);
+    }
+    Ok(())
+}
+
+/// Writes the castling rights with rook files, or with `KQkq` where that is unambiguous
+/// unless `shredder` is set
+fn castling_field(board: &Board, shredder: bool) -> String {
+    let mut field = String::new();
+    for color in [Color::White, Color::Black] {
+        let row = match color {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+        let rooks = board.colored(PieceType::Rook, color);
+        let (kingside, queenside) = board.castling_rights.get(color);
+        for (file, kingside, letter) in [(kingside, true, 'k'), (queenside, false, 'q')] {
+            let Some(file) = file else {
+                continue;
+            };
+            // Another rook further out would be the one `K` or `Q` stands for
+            let outermost = !rooks
+                .squares()
+                .any(|x| x.row == row && if kingside { x.col > file } else { x.col < file });
+            let c = if outermost && !shredder {
+                letter
+            } else {
+                (file + b'a') as char
+            };
+            field.push(match color {
+                Color::White => c.to_ascii_uppercase(),
+                Color::Black => c,
+            });
+        }
+    }
+    if field.is_empty() {
+        field.push('-');
+    }
+    field
+}
+
+/// Whether any castling right needs Chess960 rules, because the king isn't on the e file
+/// or the rook isn't in the corner
+fn is_chess960(board: &Board) -> bool {
+    [Color::White, Color::Black].into_iter().any(|color| {
+        let (kingside, queenside) = board.castling_rights.get(color);
+        let king = board.colored(PieceType::King, color).first();
+        (kingside.is_some() || queenside.is_some())
+            && (king.is_some_and(|king| king.col != 4)
+                || kingside.is_some_and(|x| x != 7)
+                || queenside.is_some_and(|x| x != 0))
+    })
+}
+
+/// The square has to be behind a pawn of the side that just moved,
+/// with both the square and the pawn's starting square empty
+fn parse_en_passant(
+    board: &Board,
+    (offset, raw): (usize, &str),
+) -> Result<Option<Square>, FenError> {
+    if raw == "-" {
+        return Ok(None);
+    }
+    let error = FenError::InvalidEnPassant { offset };
+    let &[file @ 'a'..='h', rank @ '1'..='8'] = raw.chars().collect::<Vec<char>>().as_slice()
+    else {
+        return Err(error);
+    };
+    let square = Square::from_chess_square([file, rank]);
+    let (row, forward) = match board.turn {
+        Color::White => (5, -1),
+        Color::Black => (2, 1),
+    };
+    let pawn = Piece::new(PieceType::Pawn, board.turn.opposite());
+    let valid = square.row == row
+        && board.get(square).is_none()
+        && square
+            .offset(-forward, 0)
+            .is_some_and(|x| board.get(x).is_none())
+        && square
+            .offset(forward, 0)
+            .is_some_and(|x| board.get(x) == Some(pawn));
+    valid.then_some(Some(square)).ok_or(error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FenError, FenField};
+    use crate::position::{Move, Position, Square};
+
+    #[test]
+    fn valid() {
+        let pos =
+            Position::try_from("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2")
+                .unwrap();
+        assert_eq!(pos.board.en_passant, Some(Square::new(5, 4)));
+        assert_eq!(pos.movecount, 2);
+        let pos = Position::try_from("r3k2r/8/8/8/8/8/8/R3K2R b KQ - 12 40").unwrap();
+        assert_eq!(pos.board.castling_rights.to_string(), "KQ");
+        assert_eq!(pos.halfmove_count, 12);
+        let pos = Position::try_from("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(pos.board.castling_rights.to_string(), "-");
+    }
+
+    #[test]
+    fn castling_files() {
+        for castling in ["HAha", "KQha", "AHah"] {
+            let pos = Position::try_from(format!("r3k2r/8/8/8/8/8/8/R3K2R w {castling} - 0 1"));
+            assert_eq!(pos.unwrap().board.castling_rights.to_string(), "KQkq");
+        }
+        assert_eq!(
+            Position::try_from("r3k2r/8/8/8/8/8/8/R3K2R w KG - 0 1").unwrap_err(),
+            FenError::InconsistentCastling {
+                found: 'G',
+                offset: 27
+            }
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            "4k3/8/8/8/8/8/8/4K2R b K - 17 63",
+            "4k3/8/8/8/8/8/8/4K2R w K - 300 213",
+        ] {
+            assert_eq!(Position::try_from(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn after_moves() {
+        let mut pos = Position::default();
+        pos.make_move(Move::new(Square::new(1, 4), Square::new(3, 4)));
+        assert_eq!(
+            pos.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        pos.make_move(Move::new(Square::new(7, 6), Square::new(5, 5)));
+        pos.make_move(Move::new(Square::new(0, 4), Square::new(1, 4)));
+        assert_eq!(
+            pos.to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("", FenField::Placement, 0),
+            ("8/8/8/8/8/8/8/8", FenField::SideToMove, 15),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0", FenField::FullmoveNumber, 27),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 1 x",
+                FenField::FullmoveNumber,
+                30,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 0",
+                FenField::FullmoveNumber,
+                28,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - -1 1",
+                FenField::HalfmoveClock,
+                26,
+            ),
+            ("4k3/8/8/8/8/8/8/4X3 w - - 0 1", FenField::Placement, 17),
+            ("4k3/8/8/8/8/8/8/4K4 w - - 0 1", FenField::Placement, 16),
+            ("4k3/8/8/8/8/8/4K3 w - - 0 1", FenField::Placement, 0),
+            ("4k3/8/8/8/8/8/8/4KK2 w - - 0 1", FenField::Placement, 0),
+            ("8/8/8/8/8/8/8/4K3 w - - 0 1", FenField::Placement, 0),
+            ("4k2P/8/8/8/8/8/8/4K3 w - - 0 1", FenField::Placement, 3),
+            ("4k3/8/8/8/8/8/8/4K3 x - - 0 1", FenField::SideToMove, 20),
+            ("4k3/8/8/8/8/8/8/4K2r b - - 0 1", FenField::SideToMove, 21),
+            ("4k3/8/8/8/8/8/8/4K3 w KQ - 0 1", FenField::Castling, 22),
+            ("4k3/8/8/8/8/8/8/R3K3 w QQ - 0 1", FenField::Castling, 24),
+            ("4k3/8/8/8/8/8/8/4K3 w X - 0 1", FenField::Castling, 22),
+            ("4k3/8/8/8/8/8/8/4K3 w - e3 0 1", FenField::EnPassant, 24),
+            ("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1", FenField::EnPassant, 26),
+            ("4k3/8/8/8/8/8/8/4K3 w - e9 0 1", FenField::EnPassant, 24),
+            // Offsets count every character of the whitespace between fields
+            ("4k3/8/8/8/8/8/8/4K3\tw  X - 0 1", FenField::Castling, 23),
+            (
+                " 4k3/8/8/8/8/8/8/4K3 w - -\t0 0",
+                FenField::FullmoveNumber,
+                29,
+            ),
+        ];
+        for (fen, field, offset) in cases {
+            let error = Position::try_from(fen).unwrap_err();
+            assert_eq!(
+                (error.field(), error.offset()),
+                (field, offset),
+                "{fen}: {error}"
+            );
+        }
+    }
+}
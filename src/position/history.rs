@@ -0,0 +1,280 @@
+//! Undo and redo of edits to the game, kept apart from moving through it.
+//! An edit only keeps the parts of the game it changed, so playing a move
+//! costs about as much to undo as the move itself
+
+use super::{
+    tree::{Annotations, NodeId},
+    GameTree, Position,
+};
+
+/// How many edits can be undone
+const LIMIT: usize = 200;
+
+/// A part of the game as it was before an edit. Undoing swaps it with the game,
+/// which leaves it holding the part as it was after the edit, for redoing
+#[derive(Debug, Clone)]
+enum Change {
+    /// The moves after a node, in their order
+    Children(NodeId, Vec<NodeId>),
+    /// The comments, glyphs and shapes of a move
+    Annotations(NodeId, Annotations),
+    /// The starting position and the whole tree, when the game got a new start
+    Game(String, GameTree),
+}
+
+/// The changes of one edit
+#[derive(Debug, Clone)]
+struct Edit {
+    changes: Vec<Change>,
+    /// Where the edit was made, which undoing it goes back to.
+    /// After undoing it is where the edit was undone, which redoing goes back to
+    cursor: NodeId,
+}
+
+/// The edits that can be undone and the undone ones that can be redone
+#[derive(Debug, Clone, Default)]
+pub(super) struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// The changes of the edit being made, `None` outside of one
+    changes: Option<Vec<Change>>,
+}
+
+impl Position {
+    /// Runs an edit and records the changes it saved if it changed anything.
+    /// A new edit can't be combined with undone ones, so they can't be redone any more.
+    /// An edit run inside another one is part of it
+    pub(super) fn edit(&mut self, edit: impl FnOnce(&mut Self) -> bool) -> bool {
+        if self.history.changes.is_some() {
+            return edit(self);
+        }
+        let cursor = self.cursor;
+        self.history.changes = Some(Vec::new());
+        let changed = edit(self);
+        let history = &mut self.history;
+        let saved = history.changes.take().unwrap_or_default();
+        if changed {
+            if history.undo.len() == LIMIT {
+                history.undo.remove(0);
+            }
+            history.undo.push(Edit {
+                changes: saved,
+                cursor,
+            });
+            history.redo.clear();
+        }
+        changed
+    }
+
+    /// Remembers the moves after a node before the edit being made changes them
+    pub(super) fn save_children(&mut self, id: NodeId) {
+        if let Some(changes) = &mut self.history.changes {
+            changes.push(Change::Children(id, self.tree.children(id).to_vec()));
+        }
+    }
+
+    /// Changes the comments, glyphs or shapes of a move as an edit
+    pub(super) fn annotate(&mut self, id: NodeId, annotate: impl FnOnce(&mut GameTree)) {
+        self.edit(|pos| {
+            if let Some(changes) = &mut pos.history.changes {
+                let annotations = pos.tree.node(id).annotations().clone();
+                changes.push(Change::Annotations(id, annotations));
+            }
+            annotate(&mut pos.tree);
+            true
+        });
+    }
+
+    /// Gives the game a new starting position and tree as an edit.
+    /// The old ones are kept for undoing without being copied
+    pub(super) fn replace_game(&mut self, starting_position: String, tree: GameTree) {
+        self.edit(|pos| {
+            let starting_position =
+                std::mem::replace(&mut pos.starting_position, starting_position);
+            let tree = std::mem::replace(&mut pos.tree, tree);
+            if let Some(changes) = &mut pos.history.changes {
+                changes.push(Change::Game(starting_position, tree));
+            }
+            true
+        });
+    }
+
+    /// Swaps the parts of the game an edit changed with the ones it holds, last change first,
+    /// and goes to the node it holds
+    fn swap(&mut self, edit: &mut Edit) {
+        for change in edit.changes.iter_mut().rev() {
+            match change {
+                Change::Children(id, children) => {
+                    std::mem::swap(self.tree.children_mut(*id), children);
+                }
+                Change::Annotations(id, annotations) => {
+                    std::mem::swap(self.tree.annotations_mut(*id), annotations);
+                }
+                Change::Game(starting_position, tree) => {
+                    std::mem::swap(&mut self.starting_position, starting_position);
+                    std::mem::swap(&mut self.tree, tree);
+                }
+            }
+        }
+        // Redoing swaps the changes in the order they were made
+        edit.changes.reverse();
+        let cursor = std::mem::replace(&mut edit.cursor, self.cursor);
+        self.displaced = None;
+        self.go_to(cursor);
+    }
+
+    /// Reverts the last edit of the game: a move added, a variation deleted or reordered,
+    /// a comment, glyph or shape changed. Moving through the game isn't an edit.
+    /// Returns false if there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let Some(mut edit) = self.history.undo.pop() else {
+            return false;
+        };
+        self.swap(&mut edit);
+        self.history.redo.push(edit);
+        true
+    }
+
+    /// Makes an undone edit again.
+    /// Returns false if there is nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let Some(mut edit) = self.history.redo.pop() else {
+            return false;
+        };
+        self.swap(&mut edit);
+        self.history.undo.push(edit);
+        true
+    }
+
+    /// Whether there is an edit to undo
+    #[must_use]
+    pub const fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    /// Whether there is an undone edit to redo
+    #[must_use]
+    pub const fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::KeyCode;
+
+    use super::Change;
+    use crate::position::{testing::play, GameTree, Move, Nag, Position};
+
+    #[test]
+    fn undo_redo() {
+        let mut pos = Position::default();
+        assert!(!pos.undo());
+        play(&mut pos, &["e4", "e5"]);
+        pos.go_to(pos.tree().mainline()[0]);
+        play(&mut pos, &["c5"]);
+        let sicilian = pos.cursor();
+
+        // Going back and forth and following known moves isn't an edit
+        pos.go_to(GameTree::ROOT);
+        play(&mut pos, &["e4"]);
+        pos.go_to_end();
+
+        pos.go_to(sicilian);
+        assert!(pos.delete_variation());
+        assert_eq!(pos.tree().children(pos.cursor()).len(), 1);
+        assert!(pos.undo());
+        assert_eq!(pos.cursor(), sicilian);
+        assert_eq!(pos.tree().children(pos.tree().mainline()[0]).len(), 2);
+        assert!(pos.redo());
+        assert_eq!(pos.tree().children(pos.cursor()).len(), 1);
+        assert!(!pos.redo());
+        assert!(pos.undo());
+
+        pos.set_comment(sicilian, false, "Sharp");
+        pos.add_nag(sicilian, Nag::GOOD);
+        assert!(pos.undo());
+        assert!(pos.tree().node(sicilian).nags().is_empty());
+        assert!(pos.undo());
+        assert_eq!(pos.tree().node(sicilian).comment(), "");
+        // A new edit forgets the undone ones
+        assert!(pos.promote_to_mainline());
+        assert!(!pos.can_redo());
+        assert!(pos.undo());
+
+        // Undoing the moves goes back to the starting position
+        assert!(pos.undo());
+        assert!(pos.undo());
+        assert!(pos.undo());
+        assert!(!pos.can_undo());
+        assert!(pos.tree().children(GameTree::ROOT).is_empty());
+        assert_eq!(pos.to_fen(), Position::default().to_fen());
+    }
+
+    #[test]
+    fn single_steps() {
+        let mut pos = Position::default();
+        // Tag pairs aren't edits of the game
+        pos.set_header("White", "Carlsen");
+        assert!(!pos.can_undo());
+        play(&mut pos, &["e4"]);
+        // Cycling a glyph and typing a comment are one edit each
+        pos.handle_keyboard(KeyCode::Char('!'));
+        pos.handle_keyboard(KeyCode::Char('!'));
+        for code in "cgood".chars().map(KeyCode::Char) {
+            pos.handle_keyboard(code);
+        }
+        pos.handle_keyboard(KeyCode::Enter);
+        assert!(pos.undo());
+        assert!(pos.undo());
+        assert_eq!(pos.tree().node(pos.cursor()).nags(), [Nag::GOOD]);
+
+        pos.go_to(GameTree::ROOT);
+        pos.insert_move(Move::parse(&pos, "d4").unwrap());
+        pos.insert_move(Move::parse(&pos, "d5").unwrap());
+        pos.delete_previous_moves();
+        assert_eq!(pos.tree().mainline().len(), 1);
+        assert!(pos.undo());
+        assert_eq!(pos.starting_fen(), Position::default().starting_fen());
+        assert!(pos.undo());
+        assert!(pos.undo());
+        assert_eq!(pos.tree().mainline().len(), 1);
+        assert_eq!(pos.tree().children(GameTree::ROOT).len(), 1);
+
+        // Redoing goes through the same games forward
+        assert!(pos.redo());
+        assert!(pos.redo());
+        assert_eq!(pos.tree().mainline().len(), 3);
+        assert_eq!(pos.tree().children(GameTree::ROOT).len(), 1);
+        assert!(pos.redo());
+        assert_eq!(pos.cursor(), GameTree::ROOT);
+        assert_eq!(pos.tree().mainline().len(), 1);
+        assert!(!pos.redo());
+    }
+
+    #[test]
+    fn moves_only_keep_what_changed() {
+        let mut pos = Position::default();
+        play(&mut pos, &["e4", "e5", "Nf3", "Nc6", "Bb5"]);
+        // Each move only changed the moves after the one before it
+        for edit in &pos.history.undo {
+            assert!(matches!(edit.changes[..], [Change::Children(_, ref old)] if old.is_empty()));
+        }
+        pos.go_to(pos.tree().mainline()[2]);
+        play(&mut pos, &["Nf6"]);
+        let petroff = pos.cursor();
+        pos.set_comment(petroff, false, "The Petroff");
+        pos.go_to(GameTree::ROOT);
+        assert!(pos.undo());
+        assert_eq!(pos.tree().node(petroff).comment(), "");
+        assert!(pos.undo());
+        assert_eq!(pos.tree().children(pos.cursor()).len(), 1);
+        // Redoing goes back to where the edit was undone
+        assert!(pos.redo());
+        assert_eq!(pos.cursor(), petroff);
+        assert!(pos.redo());
+        assert_eq!(pos.cursor(), GameTree::ROOT);
+        assert_eq!(pos.tree().node(petroff).comment(), "The Petroff");
+        assert_eq!(pos.tree().children(pos.tree().mainline()[2]).len(), 2);
+    }
+}
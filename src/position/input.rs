@@ -1,40 +1,478 @@
-use crossterm::event::{KeyCode, MouseEvent};
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::layout::Rect;
 
 use super::{
+    annotation::{CommentEdit, EditResult, Nag},
+    board::PROMOTIONS,
+    draw::promotion_area,
     moves::{Move, Square},
-    Position,
+    shapes::{Shape, ShapeColor},
+    tree::GameTree,
+    PieceType, Position,
 };
 
+/// The glyphs each key cycles through
+const NAG_KEYS: [(char, &[Nag]); 5] = [
+    ('!', &[Nag::GOOD, Nag::BRILLIANT, Nag::INTERESTING]),
+    ('?', &[Nag::MISTAKE, Nag::BLUNDER, Nag::DUBIOUS]),
+    ('+', &[Nag(14), Nag(16), Nag(18)]),
+    ('-', &[Nag(15), Nag(17), Nag(19)]),
+    ('=', &[Nag::EQUAL]),
+];
+
+/// A move being typed at the prompt
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct MoveEntry {
+    /// The text so far
+    pub text: String,
+    /// Why the text couldn't be played when it was entered
+    pub error: Option<String>,
+}
+
+/// The square of the small board at a point on the screen
+fn board_square(rect: Rect, column: u16, row: u16) -> Option<Square> {
+    // NOTE: This assumes that the chessboard is located top left
+    let row = u8::try_from(row.checked_sub(rect.y)?).ok()?;
+    let column = u8::try_from(column.checked_sub(rect.x)?).ok()?;
+    (row < 8 && column < 16).then(|| Square::new(7 - row, column / 2))
+}
+
+/// The color of a shape drawn while holding modifiers, the same ones Lichess uses:
+/// shift or control for red, alt for blue and both for yellow
+const fn shape_color(modifiers: KeyModifiers) -> ShapeColor {
+    let red = modifiers.intersects(KeyModifiers::SHIFT.union(KeyModifiers::CONTROL));
+    match (red, modifiers.contains(KeyModifiers::ALT)) {
+        (false, false) => ShapeColor::Green,
+        (true, false) => ShapeColor::Red,
+        (false, true) => ShapeColor::Blue,
+        (true, true) => ShapeColor::Yellow,
+    }
+}
+
 impl Position {
-    /// Handles clicking on the chessboard
+    /// Handles the mouse on the chessboard or the moves.
+    /// A left click on a piece selects it and one on another square afterwards moves it there.
+    /// Clicking a move shows the position after it.
+    /// Dragging with the right button draws an arrow and right clicking a square colors it,
+    /// as does a left click while holding a modifier. The modifiers pick the color
     pub fn handle_mouse(&mut self, rect: &Rect, mouse: MouseEvent) {
-        self.highlighted = None;
-        let row = i32::from(mouse.row) - i32::from(rect.x);
-        let column = i32::from(mouse.column) - i32::from(rect.y);
-        if row < 0 || column < 0 {
-            return;
+        let square = board_square(*rect, mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) if mouse.modifiers.is_empty() => {
+                self.click(*rect, mouse);
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(square) = square.filter(|_| self.promotion.is_none()) {
+                    self.toggle_shape(Shape::square(shape_color(mouse.modifiers), square));
+                }
+            }
+            MouseEventKind::Down(MouseButton::Right) => self.drawing = square,
+            MouseEventKind::Up(MouseButton::Right) => {
+                if let (Some(from), Some(to)) = (self.drawing.take(), square) {
+                    self.toggle_shape(Shape {
+                        color: shape_color(mouse.modifiers),
+                        from,
+                        to,
+                    });
+                }
+            }
+            _ => (),
         }
-        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-        let row = row as u16;
-        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-        let column = column as u16;
+    }
 
-        if row >= rect.width || column >= rect.height {
+    /// Selects pieces, moves them and picks promotions with left clicks
+    fn click(&mut self, rect: Rect, mouse: MouseEvent) {
+        if let Some(r#move) = self.promotion.take() {
+            let area = promotion_area(rect);
+            // The pieces are shown on the line below the top border, two columns each
+            if mouse.row == area.y + 1 && mouse.column > area.x {
+                let index = usize::from(mouse.column - area.x - 1) / 2;
+                if let Some(&piece) = PROMOTIONS.get(index) {
+                    self.play(r#move.with_promotion(piece));
+                }
+            }
+            return;
+        }
+        if let Some(id) = self.node_at(mouse.column, mouse.row) {
+            self.go_to(id);
             return;
         }
-        // NOTE: This assumes that the chessboard is located top left
-        let x = row;
-        let y = column / 2;
-        self.highlighted = Some(Square::new(x as u8, y as u8));
+        let Some(square) = board_square(rect, mouse.column, mouse.row) else {
+            self.highlighted = None;
+            return;
+        };
+        if let Some(start) = self.highlighted.take() {
+            let moves = self.moves_between(start, square);
+            if !moves.is_empty() && !self.status().is_over() {
+                if moves.len() > 1 {
+                    self.promotion = Some(moves[0]);
+                } else {
+                    self.play(moves[0]);
+                }
+                return;
+            }
+            if start == square {
+                return;
+            }
+        }
+        self.highlighted = Some(square);
     }
 
-    /// Handles keyboard events
-    pub fn handle_keyboard(&mut self, code: KeyCode) {
-        if !matches!(code, KeyCode::Char(' ')) {
-            return;
+    /// Handles keyboard events.
+    /// `c` and `C` edit the comment after and before the current move,
+    /// `!`, `?`, `+` and `-` cycle through glyphs and `=` toggles the one for equality.
+    /// Space opens a prompt for typing a move in SAN, long algebraic or UCI notation,
+    /// which Enter plays like a move made on the board.
+    /// Moves aren't accepted once the game is over
+    /// # Return values
+    /// returns true if the key was used up, e.g. by the promotion picker or one of the popups
+    pub fn handle_keyboard(&mut self, code: KeyCode) -> bool {
+        if let Some(r#move) = self.promotion.take() {
+            let piece = match code {
+                KeyCode::Char(c) => PieceType::from(c).filter(|x| PROMOTIONS.contains(x)),
+                _ => None,
+            };
+            if let Some(piece) = piece {
+                self.play(r#move.with_promotion(piece));
+            } else if code != KeyCode::Esc {
+                // Other keys leave the picker open
+                self.promotion = Some(r#move);
+            }
+            return true;
+        }
+        if let Some(mut edit) = self.comment_edit.take() {
+            match edit.handle_key(code) {
+                EditResult::Editing => self.comment_edit = Some(edit),
+                EditResult::Save => self.set_comment(edit.node, edit.before, edit.text),
+                EditResult::Cancel => (),
+            }
+            return true;
+        }
+        if let Some(entry) = self.move_entry.take() {
+            self.enter_move(entry, code);
+            return true;
+        }
+        match code {
+            KeyCode::Char(c @ ('c' | 'C')) => {
+                let before = c == 'C';
+                // The root has no move to put a comment in front of
+                if !(before && self.cursor == GameTree::ROOT) {
+                    let node = self.tree.node(self.cursor);
+                    let text = if before {
+                        node.comment_before()
+                    } else {
+                        node.comment()
+                    };
+                    self.comment_edit = Some(CommentEdit::new(self.cursor, before, text));
+                }
+                return true;
+            }
+            KeyCode::Char(c) if self.cursor != GameTree::ROOT => {
+                if let Some((_, cycle)) = NAG_KEYS.iter().find(|(key, _)| *key == c) {
+                    self.cycle_nag(cycle);
+                    return true;
+                }
+            }
+            _ => (),
+        }
+        if code == KeyCode::Char('o') {
+            self.overlay = self.overlay.next();
+            return true;
+        }
+        if code != KeyCode::Char(' ') || self.status().is_over() {
+            return false;
+        }
+        self.move_entry = Some(MoveEntry::default());
+        true
+    }
+
+    /// Types into the move prompt. Enter plays the move, or shows why it can't be played
+    /// and leaves the prompt open, and Esc closes it
+    fn enter_move(&mut self, mut entry: MoveEntry, code: KeyCode) {
+        match code {
+            KeyCode::Enter => match Move::parse(self, entry.text.trim()) {
+                Ok(r#move) => {
+                    self.play(r#move);
+                    return;
+                }
+                Err(error) => entry.error = Some(error.to_string()),
+            },
+            KeyCode::Esc => return,
+            KeyCode::Backspace => {
+                entry.text.pop();
+            }
+            KeyCode::Char(c) => entry.text.push(c),
+            _ => (),
+        }
+        self.move_entry = Some(entry);
+    }
+}
+
+impl Position {
+    /// Makes a move picked on the board, inserting it if insert mode is on
+    fn play(&mut self, r#move: Move) {
+        if self.inserting {
+            self.insert_move(r#move);
+        } else {
+            self.make_move(r#move);
+        }
+    }
+
+    /// Moves the current move from one glyph of `cycle` to the next,
+    /// from none to the first and from the last to none
+    fn cycle_nag(&mut self, cycle: &[Nag]) {
+        let nags = self.tree.node(self.cursor).nags();
+        let current = cycle.iter().position(|nag| nags.contains(nag));
+        let next = current.map_or(Some(0), |x| Some(x + 1).filter(|&x| x < cycle.len()));
+        let id = self.cursor;
+        self.annotate(id, |tree| {
+            if let Some(current) = current {
+                tree.remove_nag(id, cycle[current]);
+            }
+            if let Some(next) = next {
+                tree.add_nag(id, cycle[next]);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+    use ratatui::{backend::TestBackend, layout::Rect, Terminal};
+
+    use crate::position::{
+        GameTree, Move, Nag, PieceType, Position, ScreenLayout, Shape, ShapeColor, Square,
+    };
+
+    fn click(pos: &mut Position, column: u16, row: u16) {
+        let event = MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+        pos.handle_mouse(&Rect::new(0, 0, 80, 24), event);
+    }
+
+    /// Draws the position the size [`click`] expects, which is where moves get their spots
+    fn draw(pos: &Position) {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal
+            .draw(|frame| pos.draw(frame, frame.size(), ScreenLayout::Small))
+            .unwrap();
+    }
+
+    #[test]
+    fn click_to_move() {
+        let mut pos = Position::default();
+        // e2 then e4
+        click(&mut pos, 8, 6);
+        assert_eq!(pos.highlighted, Some(Square::new(1, 4)));
+        click(&mut pos, 8, 4);
+        assert_eq!(
+            pos.last_move().map(|x| x.to_string()).as_deref(),
+            Some("e2e4")
+        );
+        assert_eq!(pos.highlighted, None);
+        // An illegal target selects the new square instead
+        click(&mut pos, 2, 0);
+        click(&mut pos, 2, 3);
+        assert_eq!(pos.moves().len(), 1);
+        assert_eq!(pos.highlighted, Some(Square::new(4, 1)));
+    }
+
+    fn press(pos: &mut Position, keys: &str) -> bool {
+        keys.chars().all(|c| pos.handle_keyboard(KeyCode::Char(c)))
+    }
+
+    #[test]
+    fn typing_moves() {
+        let mut pos = Position::default();
+        assert!(press(&mut pos, " e4"));
+        assert!(pos.handle_keyboard(KeyCode::Enter));
+        assert_eq!(pos.tree().san(pos.cursor()), Some("e4"));
+        assert_eq!(pos.move_entry, None);
+
+        // A move that can't be played leaves the prompt open to fix it
+        press(&mut pos, " Nf6x");
+        pos.handle_keyboard(KeyCode::Enter);
+        assert!(pos.move_entry.as_ref().is_some_and(|x| x.error.is_some()));
+        pos.handle_keyboard(KeyCode::Backspace);
+        pos.handle_keyboard(KeyCode::Enter);
+        assert_eq!(pos.moves().len(), 2);
+        press(&mut pos, " a");
+        pos.handle_keyboard(KeyCode::Esc);
+        assert_eq!(pos.move_entry, None);
+        assert_eq!(pos.moves().len(), 2);
+
+        // Typed moves are inserted like the ones made on the board
+        pos.go_to(GameTree::ROOT);
+        pos.set_inserting(true);
+        press(&mut pos, " d4");
+        pos.handle_keyboard(KeyCode::Enter);
+        assert_eq!(pos.tree().children(GameTree::ROOT).len(), 2);
+        assert_eq!(pos.tree().mainline().len(), 1);
+    }
+
+    #[test]
+    fn click_moves() {
+        let mut pos = Position::default();
+        click(&mut pos, 8, 6);
+        click(&mut pos, 8, 4);
+        click(&mut pos, 8, 1);
+        click(&mut pos, 8, 3);
+        let [e4, e5] = pos.tree().mainline()[..] else {
+            panic!("expected two moves");
+        };
+        // Nothing was drawn yet, so there is no move to click
+        click(&mut pos, 30, 0);
+        assert_eq!(pos.cursor(), e5);
+        // The moves are right of the board: "   1:      e4  e5"
+        draw(&pos);
+        click(&mut pos, 30, 0);
+        assert_eq!(pos.cursor(), e4);
+        assert_eq!(pos.highlighted, None);
+        // The padding in front of e4 isn't part of it
+        click(&mut pos, 25, 0);
+        assert_eq!(pos.cursor(), e4);
+        click(&mut pos, 33, 0);
+        assert_eq!(pos.cursor(), e5);
+    }
+
+    #[test]
+    fn promotion_picker() {
+        let mut pos = Position::try_from("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        click(&mut pos, 2, 1);
+        click(&mut pos, 2, 0);
+        assert!(pos.promotion.is_some());
+        assert!(pos.handle_keyboard(KeyCode::Char('x')));
+        assert!(pos.handle_keyboard(KeyCode::Esc));
+        assert!(pos.moves().is_empty());
+
+        click(&mut pos, 2, 1);
+        click(&mut pos, 2, 0);
+        assert!(pos.handle_keyboard(KeyCode::Char('n')));
+        assert_eq!(pos.moves()[0].promotion, Some(PieceType::Knight));
+        pos.unmake_move();
+
+        click(&mut pos, 2, 1);
+        click(&mut pos, 2, 0);
+        // The rook is the second piece in the picker
+        let area = super::promotion_area(Rect::new(0, 0, 80, 24));
+        click(&mut pos, area.x + 3, area.y + 1);
+        assert_eq!(pos.moves()[0].promotion, Some(PieceType::Rook));
+    }
+
+    #[test]
+    fn annotating() {
+        let mut pos = Position::default();
+        pos.make_move(Move::parse(&pos, "e4").unwrap());
+        let e4 = pos.cursor();
+        for code in "cGood start".chars().map(KeyCode::Char) {
+            assert!(pos.handle_keyboard(code));
         }
-        self.moves
-            .push(Move::new(Square::new(0, 1), Square::new(2, 2)));
+        // Moving the board isn't possible while typing
+        assert!(pos.handle_keyboard(KeyCode::Left));
+        assert!(pos.handle_keyboard(KeyCode::Enter));
+        assert_eq!(pos.tree().node(e4).comment(), "Good start");
+        // Esc throws the changes away
+        pos.handle_keyboard(KeyCode::Char('C'));
+        pos.handle_keyboard(KeyCode::Char('x'));
+        pos.handle_keyboard(KeyCode::Esc);
+        assert_eq!(pos.tree().node(e4).comment_before(), "");
+        assert!(pos.comment_edit.is_none());
+
+        // Pressing a glyph key again picks the next glyph, until it's gone
+        pos.handle_keyboard(KeyCode::Char('!'));
+        assert_eq!(pos.tree().node(e4).nags(), [Nag::GOOD]);
+        pos.handle_keyboard(KeyCode::Char('!'));
+        assert_eq!(pos.tree().node(e4).nags(), [Nag::BRILLIANT]);
+        pos.handle_keyboard(KeyCode::Char('+'));
+        assert_eq!(pos.tree().node(e4).nags(), [Nag::BRILLIANT, Nag(14)]);
+        // A mistake replaces a good move
+        pos.handle_keyboard(KeyCode::Char('?'));
+        assert_eq!(pos.tree().node(e4).nags(), [Nag::MISTAKE, Nag(14)]);
+        for _ in 0..3 {
+            pos.handle_keyboard(KeyCode::Char('+'));
+        }
+        assert_eq!(pos.tree().node(e4).nags(), [Nag::MISTAKE]);
+    }
+
+    #[test]
+    fn drawing_shapes() {
+        let mut pos = Position::default();
+        let mouse = |kind, column, row, modifiers| MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers,
+        };
+        let area = Rect::new(0, 0, 80, 24);
+        // Dragging from e2 to e4 with the right button
+        pos.handle_mouse(
+            &area,
+            mouse(
+                MouseEventKind::Down(MouseButton::Right),
+                8,
+                6,
+                KeyModifiers::NONE,
+            ),
+        );
+        pos.handle_mouse(
+            &area,
+            mouse(
+                MouseEventKind::Up(MouseButton::Right),
+                9,
+                4,
+                KeyModifiers::NONE,
+            ),
+        );
+        // Alt-clicking d4
+        pos.handle_mouse(
+            &area,
+            mouse(
+                MouseEventKind::Down(MouseButton::Left),
+                6,
+                4,
+                KeyModifiers::ALT,
+            ),
+        );
+        assert_eq!(
+            pos.shapes(),
+            [
+                Shape {
+                    color: ShapeColor::Green,
+                    from: Square::new(1, 4),
+                    to: Square::new(3, 4),
+                },
+                Shape::square(ShapeColor::Blue, Square::new(3, 3)),
+            ]
+        );
+        // Modifier clicks don't select pieces
+        assert_eq!(pos.highlighted, None);
+        // Right clicking without dragging colors the square, shift makes it red
+        pos.handle_mouse(
+            &area,
+            mouse(
+                MouseEventKind::Down(MouseButton::Right),
+                6,
+                4,
+                KeyModifiers::SHIFT,
+            ),
+        );
+        pos.handle_mouse(
+            &area,
+            mouse(
+                MouseEventKind::Up(MouseButton::Right),
+                6,
+                4,
+                KeyModifiers::SHIFT,
+            ),
+        );
+        assert_eq!(
+            pos.shapes()[1],
+            Shape::square(ShapeColor::Red, Square::new(3, 3))
+        );
     }
 }
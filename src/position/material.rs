@@ -0,0 +1,263 @@
+//! Counts material and works out whether captures win or lose it
+
+use super::{
+    bitboard::Bitboard,
+    board::Board,
+    moves::{Move, MoveKind, Square},
+    Color, PieceType, Position,
+};
+
+/// The order SEE picks attackers in, least valuable first
+const CHEAPEST_FIRST: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+impl PieceType {
+    /// The usual point value of the piece. The king is worth nothing since it can't be traded
+    #[must_use]
+    pub const fn value(self) -> i32 {
+        match self {
+            Self::King => 0,
+            Self::Queen => 9,
+            Self::Rook => 5,
+            Self::Bishop | Self::Knight => 3,
+            Self::Pawn => 1,
+        }
+    }
+}
+
+/// The material both sides have on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Material {
+    /// Indexed by [`Color`] and then by [`PieceType`]
+    counts: [[u32; 6]; 2],
+    /// Whether each [`Color`] has bishops on both light and dark squares
+    bishop_pair: [bool; 2],
+}
+
+impl Material {
+    /// How many pieces of a type a color has
+    #[must_use]
+    pub const fn count(&self, color: Color, piece_type: PieceType) -> u32 {
+        self.counts[color as usize][piece_type as usize]
+    }
+
+    /// The total point value of the pieces of a color
+    #[must_use]
+    pub fn points(&self, color: Color) -> i32 {
+        CHEAPEST_FIRST
+            .into_iter()
+            .map(|piece_type| {
+                piece_type.value() * i32::try_from(self.count(color, piece_type)).unwrap_or(0)
+            })
+            .sum()
+    }
+
+    /// How many points white is ahead, negative if black is ahead
+    #[must_use]
+    pub fn difference(&self) -> i32 {
+        self.points(Color::White) - self.points(Color::Black)
+    }
+
+    /// Whether a color has bishops on both light and dark squares
+    #[must_use]
+    pub const fn bishop_pair(&self, color: Color) -> bool {
+        self.bishop_pair[color as usize]
+    }
+}
+
+impl Position {
+    /// Counts the pieces on the board
+    #[must_use]
+    pub fn material(&self) -> Material {
+        let sides = [Color::White, Color::Black];
+        let bishops = |color| self.board.colored(PieceType::Bishop, color);
+        let mut counts = [[0; 6]; 2];
+        for color in sides {
+            for piece_type in CHEAPEST_FIRST {
+                counts[color as usize][piece_type as usize] =
+                    self.board.colored(piece_type, color).count();
+            }
+        }
+        Material {
+            counts,
+            bishop_pair: sides.map(|color| {
+                !(bishops(color) & Bitboard::LIGHT_SQUARES).is_empty()
+                    && !(bishops(color) & !Bitboard::LIGHT_SQUARES).is_empty()
+            }),
+        }
+    }
+
+    /// The pieces of `color` that were captured since the start of the game,
+    /// most valuable first
+    #[must_use]
+    pub fn captured(&self, color: Color) -> Vec<PieceType> {
+        let mut captured = self
+            .tree
+            .path(self.cursor)
+            .windows(2)
+            .filter_map(|pair| {
+                let before = &self.tree.state(pair[0]).board;
+                let r#move = self.tree.node(pair[1]).r#move()?;
+                match r#move.kind {
+                    MoveKind::EnPassant => Some(PieceType::Pawn),
+                    MoveKind::Normal => before
+                        .get(r#move.end)
+                        .filter(|piece| piece.color == color)
+                        .map(|piece| piece.piece_type),
+                    MoveKind::Castle | MoveKind::Drop(_) => None,
+                }
+                .filter(|_| before.turn != color)
+            })
+            .collect::<Vec<_>>();
+        captured.sort_by_key(|piece_type| -piece_type.value());
+        captured
+    }
+
+    /// The static exchange evaluation of a move: the points the side to move wins or loses
+    /// if both sides keep recapturing on the target square with their cheapest piece,
+    /// and either side may stop when recapturing doesn't pay off.
+    /// Moves that don't capture are judged by whether the piece can be won on its new square
+    #[must_use]
+    pub fn see(&self, r#move: Move) -> i32 {
+        self.board.see(r#move)
+    }
+}
+
+impl Board {
+    /// See [`Position::see`]
+    pub fn see(&self, r#move: Move) -> i32 {
+        let Some(piece) = self.get(r#move.start) else {
+            return 0;
+        };
+        let target = r#move.end;
+        let mut occupied = self.occupied() ^ Bitboard::from_square(r#move.start);
+        let captured = match r#move.kind {
+            MoveKind::Normal => self.get(target).map_or(0, |x| x.piece_type.value()),
+            MoveKind::EnPassant => {
+                // The pawn taken en passant is next to the capturing one
+                occupied ^= Bitboard::from_square(Square::new(r#move.start.row, target.col));
+                PieceType::Pawn.value()
+            }
+            MoveKind::Castle | MoveKind::Drop(_) => return 0,
+        };
+        let promotion = r#move
+            .promotion
+            .map_or(0, |x| x.value() - PieceType::Pawn.value());
+        let mut gains = vec![captured + promotion];
+        // The value of the piece standing on the target square, which the next capture wins
+        let mut on_target = r#move.promotion.unwrap_or(piece.piece_type).value();
+        let mut side = piece.color.opposite();
+        loop {
+            let attackers = self.attackers_with(target, side, occupied) & occupied;
+            let Some((piece_type, square)) = CHEAPEST_FIRST.into_iter().find_map(|piece_type| {
+                (attackers & self.pieces(piece_type))
+                    .first()
+                    .map(|square| (piece_type, square))
+            }) else {
+                break;
+            };
+            // The king can only take last, when nothing recaptures
+            if piece_type == PieceType::King
+                && !(self.attackers_with(
+                    target,
+                    side.opposite(),
+                    occupied ^ Bitboard::from_square(square),
+                ) & occupied)
+                    .is_empty()
+            {
+                break;
+            }
+            gains.push(on_target - gains[gains.len() - 1]);
+            occupied ^= Bitboard::from_square(square);
+            on_target = piece_type.value();
+            side = side.opposite();
+        }
+        // Each side only recaptures if that is better than stopping
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap_or(0);
+            let previous = gains.len() - 1;
+            gains[previous] = -(-gains[previous]).max(last);
+        }
+        gains[0]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{testing::play, Color, Move, PieceType, Position};
+
+    fn see(fen: &str, r#move: &str) -> i32 {
+        let pos = Position::try_from(fen).unwrap();
+        pos.see(Move::parse(&pos, r#move).unwrap())
+    }
+
+    #[test]
+    fn static_exchange() {
+        // Undefended pawn
+        assert_eq!(
+            see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "Rxe5"),
+            1
+        );
+        // The knight is lost for a pawn after the queen recaptures
+        assert_eq!(
+            see(
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "Nxe5"
+            ),
+            -2
+        );
+        // The rook behind the queen joins in once the queen has taken
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/3Q4/3R2K1 w - - 0 1", "Qxd5"), -3);
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/3R4/3Q2K1 w - - 0 1", "Rxd5"), 1);
+        // The king takes back unless the queen behind the rook would take it
+        assert_eq!(see("4k3/4r3/8/8/8/8/4n2R/4K3 w - - 0 1", "Rxe2"), 3);
+        assert_eq!(see("4k3/4q3/4r3/8/8/8/4n2R/4K3 w - - 0 1", "Rxe2"), -2);
+        // Quiet moves lose the piece if it can just be taken
+        assert_eq!(see("4k3/8/8/3p4/8/8/8/2B1K3 w - - 0 1", "Be3"), 0);
+        assert_eq!(see("4k3/8/8/3p4/8/8/8/4KB2 w - - 0 1", "Bc4"), -3);
+        // En passant and promotions
+        assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6"), 1);
+        assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "axb8=Q"), 13);
+    }
+
+    #[test]
+    fn material() {
+        let pos = Position::try_from("4k3/8/8/3p4/8/8/8/2B1KB2 w - - 0 1").unwrap();
+        let material = pos.material();
+        assert_eq!(material.count(Color::White, PieceType::Bishop), 2);
+        assert_eq!(material.count(Color::Black, PieceType::Pawn), 1);
+        assert_eq!(material.points(Color::White), 6);
+        assert_eq!(material.difference(), 5);
+        assert!(material.bishop_pair(Color::White));
+        let default = Position::default().material();
+        assert_eq!(default.points(Color::Black), 39);
+        assert_eq!(default.difference(), 0);
+        // Two bishops on light squares aren't a pair
+        let pos = Position::try_from("4k3/8/8/8/8/8/8/1B1BK3 w - - 0 1").unwrap();
+        assert!(!pos.material().bishop_pair(Color::White));
+    }
+
+    #[test]
+    fn captured() {
+        let mut pos = Position::default();
+        play(
+            &mut pos,
+            &["e4", "d5", "exd5", "Qxd5", "Nc3", "Qxa2", "Rxa2"],
+        );
+        assert_eq!(
+            pos.captured(Color::White),
+            vec![PieceType::Pawn, PieceType::Pawn]
+        );
+        assert_eq!(
+            pos.captured(Color::Black),
+            vec![PieceType::Queen, PieceType::Pawn]
+        );
+        assert_eq!(pos.material().difference(), 8);
+    }
+}
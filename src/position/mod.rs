@@ -1,22 +1,93 @@
 //! This module does everything that has to do with storing chess-positions
 
-use self::moves::{Move, Square};
+use std::{cell::RefCell, fmt};
 
+use ratatui::layout::Rect;
+
+use self::{annotation::CommentEdit, board::Board, history::History, input::MoveEntry};
+pub use self::{
+    annotation::Nag,
+    attacks::Overlay,
+    editor::{Editor, PRESETS},
+    fen::{FenError, FenField},
+    material::Material,
+    moves::{Move, MoveKind, Square},
+    pgn::{read_pgn, read_tag, PgnError, PgnErrorKind, PgnGame},
+    san::MoveParseError,
+    shapes::{split_commands, write_commands, Shape, ShapeColor},
+    status::{GameResult, GameStatus},
+    tree::{GameTree, Node, NodeId},
+    variant::Variant,
+};
+
+mod annotation;
+mod attacks;
+mod bitboard;
+mod board;
+mod chess960;
 mod draw;
+mod editor;
+mod fen;
+mod history;
 mod input;
+mod material;
 mod moves;
+mod perft;
+mod pgn;
+mod san;
+mod shapes;
+mod status;
+#[cfg(test)]
+mod testing;
+mod tree;
+mod variant;
+mod zobrist;
 #[derive(Debug)]
 /// Stores a chess position
 pub struct Position {
     /// A fen
+    #[allow(clippy::struct_field_names)]
     starting_position: String,
-    turn: Color,
-    castling_rights: CastingRights,
-    halfmove_count: u8,
+    board: Board,
+    halfmove_count: u16,
     movecount: u64,
-    moves: Vec<Move>,
+    /// The tag pairs of the game, like `Event` and `White`
+    headers: Vec<(String, String)>,
+    /// Every move and variation played from the starting position
+    tree: GameTree,
+    /// The node of the position on the board
+    cursor: NodeId,
     highlighted: Option<Square>,
-    en_passant: Option<Square>,
+    /// Where the arrow being drawn with the right mouse button starts
+    drawing: Option<Square>,
+    /// A promotion waiting for the user to pick a piece
+    promotion: Option<Move>,
+    /// The comment open in the popup
+    comment_edit: Option<CommentEdit>,
+    /// The move being typed at the prompt
+    move_entry: Option<MoveEntry>,
+    /// Where each move was drawn the last time the moves were shown,
+    /// so a click can be matched to a move without laying them out again
+    move_spots: RefCell<Vec<(Rect, NodeId)>>,
+    /// Whether the game is Chess960, which changes how castling is written
+    chess960: bool,
+    /// What is drawn on top of the board
+    overlay: Overlay,
+    /// Whether moves made on the board are inserted in front of the continuation
+    inserting: bool,
+    /// The last inserted move and the continuation that couldn't be played after it,
+    /// which the next inserted move tries again
+    displaced: Option<(NodeId, NodeId)>,
+    /// The edits of the game that can be undone and redone
+    history: History,
+}
+
+/// The board and move counters of a node in the [`GameTree`]
+#[derive(Debug, Clone, Copy)]
+struct State {
+    board: Board,
+    halfmove_count: u16,
+    movecount: u64,
 }
 
 impl Default for Position {
@@ -26,101 +97,201 @@ impl Default for Position {
 }
 
 impl Position {
-    /// Parses a fen and create a `Position` from it
-    pub fn try_from<T>(fen: T) -> Option<Self>
-    where
-        T: Clone + Into<String>,
-    {
-        let split = fen
-            .clone()
-            .into()
-            .split_whitespace()
-            .map(std::string::ToString::to_string)
-            .collect::<Vec<String>>();
-        Some(Self {
-            starting_position: fen.into(),
-            turn: if split[1] == "w" {
-                Color::White
-            } else {
-                Color::Black
-            },
-            castling_rights: CastingRights::from(&split[2]),
-            en_passant: None,
-            halfmove_count: split[4].parse::<u8>().ok()?,
-            movecount: split[5].parse::<u64>().ok()?,
-            moves: Vec::new(),
-            highlighted: None,
-        })
+    /// The starting position of a variant
+    /// # Panics
+    /// Never, the starting positions are valid fens
+    #[must_use]
+    pub fn new(variant: Variant) -> Self {
+        Self::try_from_variant(variant.starting_fen(), variant).unwrap()
     }
 
+    /// The rules the game is played by
+    #[must_use]
+    pub const fn variant(&self) -> Variant {
+        self.board.variant
+    }
+
+    /// The fen the position was created from, before any moves were made
+    #[must_use]
+    pub fn starting_fen(&self) -> &str {
+        &self.starting_position
+    }
+
+    /// The zobrist hash of the current position.
+    /// Positions with the same pieces, side to move, castling rights and usable
+    /// en passant square have the same key
+    #[must_use]
+    pub const fn key(&self) -> u64 {
+        self.board.key()
+    }
+
+    /// The keys of every position reached so far, from the starting position to the current one
+    #[must_use]
+    pub fn key_history(&self) -> impl DoubleEndedIterator<Item = u64> + '_ {
+        self.tree
+            .path(self.cursor)
+            .into_iter()
+            .map(|id| self.tree.node(id).key())
+    }
+
+    /// How many times the current position has occurred, including now.
+    /// Only positions since the last capture or pawn move can be repetitions
+    #[must_use]
+    pub fn repetition_count(&self) -> usize {
+        let key = self.key();
+        self.key_history()
+            .rev()
+            .take(usize::from(self.halfmove_count) + 1)
+            .step_by(2)
+            .filter(|&x| x == key)
+            .count()
+    }
+
+    /// Whether the game is Chess960. The setups of [`Position::chess960`], PGNs tagged as
+    /// Chess960 and fens with castling rights that are only possible in Chess960 start such a game
+    #[must_use]
+    pub const fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Gets the piece at a given row and column.
+    /// Row 0 is the eighth rank, the same order as a fen
+    #[cfg(test)]
     fn at(&self, row: usize, col: usize) -> Option<Piece> {
-        let split = self.starting_position.split('/').nth(row)?;
-        let Ok(mut col): Result<isize, <usize as TryInto<isize>>::Error> = col.try_into() else {
-            return None;
-        };
-        for c in split.chars() {
-            match col.cmp(&0) {
-                std::cmp::Ordering::Less => return None,
-                std::cmp::Ordering::Equal => return Piece::from(c),
-                std::cmp::Ordering::Greater => match c {
-                    a @ '0'..='9' => col = col + 48 - a as isize,
-                    _ => col -= 1,
-                },
-            }
-        }
-        None
+        let row = 7u8.checked_sub(u8::try_from(row).ok()?)?;
+        let col = u8::try_from(col).ok().filter(|&col| col < 8)?;
+        self.board.get(Square::new(row, col))
     }
 }
 
-#[derive(Debug)]
+/// The files of the rooks each side may still castle with.
+/// In standard chess these are the a and h files, in Chess960 they can be any file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 struct CastingRights {
-    kingside_white: bool,
-    queenside_white: bool,
-    kingside_black: bool,
-    queenside_black: bool,
+    kingside_white: Option<u8>,
+    queenside_white: Option<u8>,
+    kingside_black: Option<u8>,
+    queenside_black: Option<u8>,
 }
 
-impl CastingRights {
-    pub fn to_string(&self) -> String {
-        [
-            if self.kingside_white { 'K' } else { '-' },
-            if self.queenside_white { 'Q' } else { '-' },
-            if self.kingside_black { 'k' } else { '-' },
-            if self.queenside_black { 'q' } else { '-' },
+impl fmt::Display for CastingRights {
+    /// Writes `KQkq` for rooks in the corners and the rook file otherwise
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rights = [
+            (self.kingside_white, 7, 'K'),
+            (self.queenside_white, 0, 'Q'),
+            (self.kingside_black, 7, 'k'),
+            (self.queenside_black, 0, 'q'),
         ]
         .into_iter()
-        .collect()
+        .filter_map(|(file, corner, c)| {
+            let file = file?;
+            Some(if file == corner {
+                c
+            } else if c.is_ascii_uppercase() {
+                (file + b'A') as char
+            } else {
+                (file + b'a') as char
+            })
+        })
+        .collect::<String>();
+        if rights.is_empty() {
+            write!(f, "-")
+        } else {
+            write!(f, "{rights}")
+        }
+    }
+}
+
+impl CastingRights {
+    /// No side may castle
+    const NONE: Self = Self {
+        kingside_white: None,
+        queenside_white: None,
+        kingside_black: None,
+        queenside_black: None,
+    };
+
+    /// Gives a color the right to castle with the rook on `file`
+    pub const fn add(&mut self, color: Color, kingside: bool, file: u8) {
+        match (color, kingside) {
+            (Color::White, true) => self.kingside_white = Some(file),
+            (Color::White, false) => self.queenside_white = Some(file),
+            (Color::Black, true) => self.kingside_black = Some(file),
+            (Color::Black, false) => self.queenside_black = Some(file),
+        }
     }
 
-    pub fn from(raw: impl Into<String>) -> Self {
-        let raw = raw.into().chars().collect::<Vec<char>>();
-        assert_eq!(raw.len(), 4);
-        Self {
-            kingside_white: raw[0] != '-',
-            kingside_black: raw[1] != '-',
-            queenside_white: raw[2] != '-',
-            queenside_black: raw[3] != '-',
+    /// Returns the files of the kingside and queenside rooks a color may castle with
+    pub const fn get(self, color: Color) -> (Option<u8>, Option<u8>) {
+        match color {
+            Color::White => (self.kingside_white, self.queenside_white),
+            Color::Black => (self.kingside_black, self.queenside_black),
+        }
+    }
+
+    /// Removes both rights of a color
+    pub const fn remove(&mut self, color: Color) {
+        match color {
+            Color::White => (self.kingside_white, self.queenside_white) = (None, None),
+            Color::Black => (self.kingside_black, self.queenside_black) = (None, None),
+        }
+    }
+
+    /// Removes the right belonging to the rook that starts on `square`
+    /// if a piece moves from or to it
+    pub fn update(&mut self, square: Square) {
+        let rights = match square.row {
+            0 => [&mut self.kingside_white, &mut self.queenside_white],
+            7 => [&mut self.kingside_black, &mut self.queenside_black],
+            _ => return,
+        };
+        for right in rights {
+            if *right == Some(square.col) {
+                *right = None;
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum PieceType {
+/// The kind of a chesspiece without its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PieceType {
+    #[allow(missing_docs)]
     King = 0,
+    #[allow(missing_docs)]
     Queen = 1,
+    #[allow(missing_docs)]
     Rook = 2,
+    #[allow(missing_docs)]
     Bishop = 3,
+    #[allow(missing_docs)]
     Knight = 4,
+    #[allow(missing_docs)]
     Pawn = 5,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Color {
+/// One of the two sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    #[allow(missing_docs)]
     White,
+    #[allow(missing_docs)]
     Black,
 }
 
 impl Color {
+    /// The other side
+    #[must_use]
+    pub const fn opposite(self) -> Self {
+        match self {
+            Self::White => Self::Black,
+            Self::Black => Self::White,
+        }
+    }
+
+    /// The color the pieces of the side are drawn in
+    #[must_use]
     pub const fn color(self) -> ratatui::style::Color {
         match self {
             Self::White => ratatui::style::Color::Rgb(255, 255, 255),
@@ -130,13 +301,29 @@ impl Color {
 }
 
 /// Defines a chesspiece with its type and color
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Piece {
     piece_type: PieceType,
     color: Color,
 }
 
 impl Piece {
+    const fn new(piece_type: PieceType, color: Color) -> Self {
+        Self { piece_type, color }
+    }
+
+    /// The letter used for the piece in a fen
+    const fn to_char(self) -> char {
+        let c = match self.piece_type.to_char() {
+            Some(c) => c,
+            None => 'P',
+        };
+        match self.color {
+            Color::White => c,
+            Color::Black => c.to_ascii_lowercase(),
+        }
+    }
+
     /// Takes a piece from a fen and converts it into a piece
     fn from(a: char) -> Option<Self> {
         if a.is_numeric() {
@@ -155,6 +342,9 @@ impl Piece {
 }
 
 impl PieceType {
+    /// The letter used for the piece in algebraic notation.
+    /// Pawns don't have one
+    #[must_use]
     pub const fn to_char(self) -> Option<char> {
         Some(match self {
             Self::King => 'K',
@@ -166,6 +356,7 @@ impl PieceType {
         })
     }
     /// Generates the unicode character resembeling the current piece
+    #[must_use]
     pub const fn to_unicode(self) -> char {
         match self {
             Self::King => '♚',
@@ -177,6 +368,8 @@ impl PieceType {
         }
     }
 
+    /// Parses a piece letter in either case
+    #[must_use]
     pub const fn from(c: char) -> Option<Self> {
         Some(match c.to_ascii_uppercase() {
             'K' => Self::King,
@@ -1,11 +1,17 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, fmt};
 
-use super::{Piece, Position};
+use super::{Color, Piece, PieceType, Position, State};
 
 impl Piece {
-    /// Gets all available moves for the current piece
+    /// Gets all available moves for the current piece.
+    /// This includes the moves of every piece on the board with the same type and color
+    #[must_use]
     pub fn get_moves(&self, position: &Position) -> HashSet<Move> {
-        todo!()
+        position
+            .get_moves()
+            .into_iter()
+            .filter(|x| position.board.get(x.start) == Some(*self))
+            .collect()
     }
 }
 
@@ -13,87 +19,350 @@ impl Position {
     /// Gets all available moves for a given chessboard
     #[must_use]
     pub fn get_moves(&self) -> HashSet<Move> {
-        // self.pos
-        todo!()
+        self.board.legal_moves().into_iter().collect()
     }
 
-    /// Makes a move in place
+    /// Gets the legal moves from one square to another.
+    /// There are several when a pawn can promote.
+    /// Castling is found both by the king's target square and by the rook's square
+    #[must_use]
+    pub fn moves_between(&self, start: Square, end: Square) -> Vec<Move> {
+        self.board
+            .legal_moves()
+            .into_iter()
+            .filter(|x| x.connects(start, end))
+            .collect()
+    }
+
+    /// Makes a move in place and moves the cursor to it.
+    /// If the move was already played from here the existing node is reused,
+    /// otherwise it continues the line or, if there is a continuation, starts a variation.
+    /// Adding a new move can be undone.
+    /// The move isn't checked for legality, but moves from an empty square
+    /// and drops of pieces that aren't in the pocket are ignored.
+    /// A plain king or pawn move that is castling or en passant is played as one,
+    /// see [`Position::classify`]
     pub fn make_move(&mut self, r#move: Move) {
-        // self.
-        todo!()
+        let r#move = self.classify(r#move);
+        self.edit(|pos| {
+            let new = pos.moving_piece(r#move).is_some()
+                && pos.tree.find_child(pos.cursor, r#move).is_none();
+            pos.apply_move(r#move);
+            new
+        });
+    }
+
+    /// The piece a move puts on its end square: the one on its start square,
+    /// or for a drop a piece from the pocket of the side to move if it has one
+    fn moving_piece(&self, r#move: Move) -> Option<Piece> {
+        match r#move.kind {
+            MoveKind::Drop(piece_type) => {
+                let turn = self.board.turn;
+                (self.board.pocket(turn, piece_type) > 0).then(|| Piece::new(piece_type, turn))
+            }
+            _ => self.board.get(r#move.start),
+        }
+    }
+
+    /// Gives a move made with [`Move::new`] the kind the board says it has,
+    /// like castling for the king moving two squares or taking its own rook.
+    /// Moves that already have a kind, and moves that aren't legal, are left as they are
+    #[must_use]
+    pub fn classify(&self, r#move: Move) -> Move {
+        let special = self
+            .board
+            .get(r#move.start)
+            .is_some_and(|piece| matches!(piece.piece_type, PieceType::King | PieceType::Pawn));
+        if r#move.kind != MoveKind::Normal || !special {
+            return r#move;
+        }
+        self.moves_between(r#move.start, r#move.end)
+            .into_iter()
+            .find(|x| x.promotion == r#move.promotion)
+            .unwrap_or(r#move)
+    }
+
+    /// Makes a move like [`Position::make_move`], as part of the edit being made if there is one
+    pub(super) fn apply_move(&mut self, r#move: Move) {
+        let Some(piece) = self.moving_piece(r#move) else {
+            return;
+        };
+        let is_capture = match r#move.kind {
+            MoveKind::Normal => self.board.get(r#move.end).is_some(),
+            MoveKind::EnPassant => true,
+            MoveKind::Castle | MoveKind::Drop(_) => false,
+        };
+        self.halfmove_count = if is_capture || piece.piece_type == PieceType::Pawn {
+            0
+        } else {
+            self.halfmove_count.saturating_add(1)
+        };
+        if self.board.turn == Color::Black {
+            self.movecount += 1;
+        }
+        self.board.play(r#move);
+        let state = State {
+            board: self.board,
+            halfmove_count: self.halfmove_count,
+            movecount: self.movecount,
+        };
+        if self.tree.find_child(self.cursor, r#move).is_none() {
+            self.save_children(self.cursor);
+        }
+        self.cursor = self.tree.add(self.cursor, r#move, state);
+    }
+
+    /// Goes back to the position before the last move and returns that move.
+    /// The move stays in the [`GameTree`](super::GameTree).
+    /// Returns `None` at the starting position
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let r#move = self.last_move()?;
+        self.go_to(self.tree.parent(self.cursor)?);
+        Some(r#move)
     }
 }
 
-#[derive(Debug, Hash, Clone, Copy)]
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
+/// A move from one square to another
 pub struct Move {
-    start: Square,
-    end: Square,
+    /// The square the moving piece stands on
+    pub start: Square,
+    /// The square the moving piece ends up on.
+    /// For castling this is the square of the rook, see [`Move::destination`]
+    pub end: Square,
+    /// The piece a pawn turns into when reaching the last rank
+    pub promotion: Option<PieceType>,
+    /// What kind of special rules the move follows
+    pub kind: MoveKind,
+}
+
+/// Tells apart moves that affect more than the start and end squares
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveKind {
+    /// Any move that isn't one of the others, including captures and promotions
+    #[default]
+    Normal,
+    /// A pawn taking a pawn that just moved two squares by moving behind it
+    EnPassant,
+    /// The king moving two squares towards a rook that jumps over it
+    Castle,
+    /// A crazyhouse piece put on the board from the pocket. The start and end are the same
+    Drop(PieceType),
 }
 
 impl Move {
-    // FIX: This doesn't account for disambiguation yet
-    pub fn get_notation(self, pos: &Position) -> Option<String> {
-        let piece = pos.at(self.start.row as usize, self.start.col as usize)?;
-        Some(
-            piece
-                .piece_type
-                .to_char()
-                .into_iter()
-                .chain(self.end.at(pos).map(|_| 'x'))
-                .chain(self.end.to_chess_square())
-                .collect::<String>(),
-        )
+    /// Creates a move without a promotion
+    #[must_use]
+    pub const fn new(start: Square, end: Square) -> Self {
+        Self {
+            start,
+            end,
+            promotion: None,
+            kind: MoveKind::Normal,
+        }
     }
 
-    pub const fn new(start: Square, end: Square) -> Self {
-        Self { start, end }
+    /// Creates a crazyhouse drop of a piece from the pocket
+    #[must_use]
+    pub const fn drop_piece(piece_type: PieceType, square: Square) -> Self {
+        Self::new(square, square).with_kind(MoveKind::Drop(piece_type))
+    }
+
+    /// Sets the kind of the move
+    #[must_use]
+    pub const fn with_kind(self, kind: MoveKind) -> Self {
+        Self { kind, ..self }
+    }
+
+    /// The square the moving piece ends up on, which for castling is the g or c file
+    #[must_use]
+    pub const fn destination(self) -> Square {
+        match self.kind {
+            MoveKind::Castle if self.end.col > self.start.col => Square::new(self.start.row, 6),
+            MoveKind::Castle => Square::new(self.start.row, 2),
+            _ => self.end,
+        }
+    }
+
+    /// Whether the move goes from `start` to `end`.
+    /// Castling also matches the king's target square if the king moves more than one file
+    /// there, so it can't be mistaken for a normal king move in Chess960
+    pub(super) fn connects(self, start: Square, end: Square) -> bool {
+        !matches!(self.kind, MoveKind::Drop(_))
+            && self.start == start
+            && (self.end == end || (self.destination() == end && start.col.abs_diff(end.col) > 1))
+    }
+
+    /// Writes the move in the coordinate notation used by uci. Drops are written like `N@f3`.
+    /// Castling is written as the king moving to its target square, or as the king
+    /// taking its own rook when `chess960` is set, like engines expect with `UCI_Chess960`
+    #[must_use]
+    pub fn to_uci(self, chess960: bool) -> String {
+        if let MoveKind::Drop(piece_type) = self.kind {
+            return format!("{}@{}", piece_type.to_char().unwrap_or('P'), self.end);
+        }
+        let end = if chess960 {
+            self.end
+        } else {
+            self.destination()
+        };
+        let mut uci = format!("{}{end}", self.start);
+        if let Some(promotion) = self.promotion.and_then(PieceType::to_char) {
+            uci.push(promotion.to_ascii_lowercase());
+        }
+        uci
+    }
+
+    /// Sets the piece a pawn promotes to
+    #[must_use]
+    pub const fn with_promotion(self, promotion: PieceType) -> Self {
+        Self {
+            promotion: Some(promotion),
+            ..self
+        }
     }
 }
 
-#[derive(Debug, Hash, Clone, Copy)]
+impl fmt::Display for Move {
+    /// Writes the move in the coordinate notation used by uci, e.g. e2e4 or e7e8q
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_uci(false))
+    }
+}
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
+/// A square on the chessboard
 pub struct Square {
-    /// Zero indexed
+    /// Zero indexed rank, 0 is the first rank
     pub row: u8,
-    /// Zero indexed
+    /// Zero indexed file, 0 is the a-file
     pub col: u8,
 }
 
 impl Square {
-    pub fn at(self, pos: &Position) -> Option<Piece> {
-        pos.at(7 - self.row as usize, self.col as usize)
+    /// Gets the piece standing on the square
+    #[must_use]
+    pub const fn at(self, pos: &Position) -> Option<Piece> {
+        pos.board.get(self)
     }
 
     /// Converts itself to coordinates e.g.
     /// (0, 0) => a1
-    /// (5, 7) => f8
+    /// (7, 5) => f8
+    #[must_use]
     pub const fn to_chess_square(self) -> [char; 2] {
-        [(self.row + b'a') as char, ((self.col + 1) ^ 0x30) as char]
+        [(self.col + b'a') as char, (self.row + b'1') as char]
     }
 
-    pub fn from_chess_square(data: &[char; 2]) -> Self {
-        Self::new(data[0] as u8 - b'a', (data[1] as u8 ^ 0x30) - 1)
+    /// Converts coordinates like `['e', '4']` into a square
+    /// # Panics
+    /// Panics if the coordinates aren't on the board
+    #[must_use]
+    pub const fn from_chess_square(data: [char; 2]) -> Self {
+        Self::new(data[1] as u8 - b'1', data[0] as u8 - b'a')
     }
 
-    pub fn new(row: u8, col: u8) -> Self {
+    /// Creates a square from a zero indexed rank and file
+    /// # Panics
+    /// Panics if either of them is outside the board
+    #[must_use]
+    pub const fn new(row: u8, col: u8) -> Self {
         assert!(row < 8);
         assert!(col < 8);
         Self { row, col }
     }
+
+    /// The index of the square when counting from a1 to h8 rank by rank
+    #[must_use]
+    pub const fn index(self) -> usize {
+        self.row as usize * 8 + self.col as usize
+    }
+
+    /// The inverse of [`Square::index`]
+    /// # Panics
+    /// Panics if the index is 64 or more
+    #[must_use]
+    pub const fn from_index(index: usize) -> Self {
+        #[allow(clippy::cast_possible_truncation)]
+        Self::new((index / 8) as u8, (index % 8) as u8)
+    }
+
+    /// Gets the square `dr` ranks and `dc` files away if it is on the board
+    #[must_use]
+    pub fn offset(self, dr: i8, dc: i8) -> Option<Self> {
+        let row = self.row.checked_add_signed(dr).filter(|&x| x < 8)?;
+        let col = self.col.checked_add_signed(dc).filter(|&x| x < 8)?;
+        Some(Self::new(row, col))
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [file, rank] = self.to_chess_square();
+        write!(f, "{file}{rank}")
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::position::Position;
+    use crate::position::{Color, PieceType, Position};
 
-    use super::Move;
+    use super::{Move, MoveKind, Square};
 
     #[test]
     fn notation() {
         let pos = Position::default();
-        let r#move = Move {
-            start: super::Square { row: 0, col: 1 },
-            end: super::Square { row: 2, col: 2 },
-        };
+        let r#move = Move::new(
+            super::Square { row: 0, col: 1 },
+            super::Square { row: 2, col: 2 },
+        );
         assert_eq!(r#move.get_notation(&pos), Some("Nc3".to_string()));
     }
+
+    #[test]
+    fn make_unmake() {
+        let mut pos = Position::try_from(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let before = (pos.board, pos.halfmove_count, pos.movecount);
+        let castle = Move::parse(&pos, "O-O").unwrap();
+        assert_eq!(castle.kind, MoveKind::Castle);
+        pos.make_move(castle);
+        assert_eq!(pos.at(7, 5).map(|x| x.piece_type), Some(PieceType::Rook));
+        assert_eq!(pos.board.turn, Color::Black);
+        assert_eq!((pos.halfmove_count, pos.movecount), (1, 1));
+        // a double pawn push resets the halfmove clock and allows en passant
+        pos.make_move(Move::new(Square::new(6, 2), Square::new(4, 2)));
+        assert_eq!(pos.board.en_passant, Some(Square::new(5, 2)));
+        assert_eq!((pos.halfmove_count, pos.movecount), (0, 2));
+        let en_passant = Move::parse(&pos, "dxc6").unwrap();
+        assert_eq!(en_passant.kind, MoveKind::EnPassant);
+        pos.make_move(en_passant);
+        assert_eq!(pos.at(3, 2), None);
+
+        assert_eq!(pos.unmake_move(), Some(en_passant));
+        // The same moves made from their squares alone are castling and en passant too
+        pos.make_move(Move::new(Square::new(4, 3), Square::new(5, 2)));
+        assert_eq!(pos.last_move(), Some(en_passant));
+        assert_eq!(pos.at(4, 2), None);
+        pos.unmake_move();
+        assert_eq!(pos.unmake_move().map(|x| x.end), Some(Square::new(4, 2)));
+        assert_eq!(pos.unmake_move(), Some(castle));
+        assert_eq!(pos.unmake_move(), None);
+        assert_eq!((pos.board, pos.halfmove_count, pos.movecount), before);
+        pos.make_move(Move::new(Square::new(0, 4), Square::new(0, 6)));
+        assert_eq!(pos.last_move(), Some(castle));
+        assert_eq!(pos.at(7, 5).map(|x| x.piece_type), Some(PieceType::Rook));
+    }
+
+    #[test]
+    fn en_passant_discovered_check() {
+        // Taking en passant would expose the king along the fifth rank
+        let pos = Position::try_from("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1").unwrap();
+        assert!(pos
+            .get_moves()
+            .iter()
+            .all(|x| x.end.to_chess_square() != ['d', '6']));
+    }
 }
@@ -0,0 +1,58 @@
+//! Counts the leaf nodes of the move tree to verify move generation
+
+#[cfg(test)]
+use super::Variant;
+use super::{board::Board, Move, Position};
+
+impl Position {
+    /// Counts every sequence of `depth` legal moves from the current position
+    #[must_use]
+    pub fn perft(&self, depth: usize) -> u64 {
+        perft(&self.board, depth)
+    }
+
+    /// Splits [`Position::perft`] up by the first move.
+    /// The moves are sorted by their uci notation
+    #[must_use]
+    pub fn divide(&self, depth: usize) -> Vec<(Move, u64)> {
+        let mut divide = self
+            .board
+            .legal_moves()
+            .into_iter()
+            .map(|r#move| {
+                let mut next = self.board;
+                next.play(r#move);
+                (r#move, perft(&next, depth.saturating_sub(1)))
+            })
+            .collect::<Vec<_>>();
+        divide.sort_by_cached_key(|(r#move, _)| r#move.to_string());
+        divide
+    }
+}
+
+fn perft(board: &Board, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = board.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|r#move| {
+            let mut next = *board;
+            next.play(r#move);
+            perft(&next, depth - 1)
+        })
+        .sum()
+}
+
+/// Checks [`Position::perft`] of a fen against the expected counts, starting at depth one
+#[cfg(test)]
+pub(super) fn check_perft(variant: Variant, fen: &str, expected: &[u64]) {
+    let pos = Position::try_from_variant(fen, variant).unwrap();
+    for (depth, &nodes) in expected.iter().enumerate() {
+        assert_eq!(pos.perft(depth + 1), nodes, "{fen} at depth {}", depth + 1);
+    }
+}
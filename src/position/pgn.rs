@@ -0,0 +1,918 @@
+//! Reading and writing games in portable game notation: the tag pairs,
+//! the moves with their variations, comments and glyphs, and the result
+
+use std::{
+    error::Error,
+    fmt::{self, Write},
+};
+
+use super::{
+    annotation::Nag,
+    fen::FenError,
+    moves::Move,
+    san::MoveParseError,
+    shapes::{split_commands, write_commands, Shape},
+    tree::{GameTree, NodeId},
+    variant::Variant,
+    Position,
+};
+
+/// The tokens that end a game
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// The tags every exported game starts with, in this order, and their values when unknown
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// The tags that are written from the position instead of the stored ones
+const SETUP_TAGS: [&str; 3] = ["Variant", "SetUp", "FEN"];
+
+/// The longest line of movetext, so the lines fit in 80 columns
+const LINE_WIDTH: usize = 79;
+
+/// What is wrong with a part of a PGN
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnErrorKind {
+    /// A tag pair isn't written like `[Name "value"]`
+    InvalidTag,
+    /// The `FEN` tag doesn't hold a valid position, so the game starts from the usual one
+    InvalidFen(FenError),
+    /// The `Variant` tag names a variant that isn't supported
+    UnsupportedVariant(String),
+    /// A comment in braces isn't closed before the end
+    UnterminatedComment,
+    /// Something that isn't a move, a move number, a glyph or a result
+    UnexpectedToken(String),
+    /// A move couldn't be played. The rest of its variation is skipped
+    InvalidMove(MoveParseError),
+    /// A variation starts before the move it is an alternative to. It is skipped
+    VariationWithoutMove,
+    /// A `)` without a `(` before it
+    UnmatchedParenthesis,
+    /// A variation isn't closed before the game ends
+    UnclosedVariation,
+}
+
+impl fmt::Display for PgnErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTag => f.write_str("the tag pair isn't written like [Name \"value\"]"),
+            Self::InvalidFen(error) => write!(f, "the FEN tag is invalid: {error}"),
+            Self::UnsupportedVariant(name) => write!(f, "the variant '{name}' isn't supported"),
+            Self::UnterminatedComment => f.write_str("the comment isn't closed"),
+            Self::UnexpectedToken(token) => write!(f, "unexpected '{token}'"),
+            Self::InvalidMove(error) => error.fmt(f),
+            Self::VariationWithoutMove => f.write_str("the variation comes before any move"),
+            Self::UnmatchedParenthesis => f.write_str("')' without a variation to close"),
+            Self::UnclosedVariation => f.write_str("the variation isn't closed"),
+        }
+    }
+}
+
+/// Describes why part of a PGN couldn't be read and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnError {
+    /// The line of the problem, counting from 1
+    pub line: usize,
+    /// The column of the problem in characters, counting from 1
+    pub column: usize,
+    /// What the problem is
+    pub kind: PgnErrorKind,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.kind
+        )
+    }
+}
+
+impl Error for PgnError {}
+
+/// A game read from a PGN together with the problems that were skipped over
+#[derive(Debug)]
+pub struct PgnGame {
+    /// The game at its starting position, with its tags and every move
+    pub position: Position,
+    /// The problems in the order they were found
+    pub errors: Vec<PgnError>,
+    /// The byte offset after the game, where the next one in the text can start
+    pub end: usize,
+}
+
+/// Goes through the text character by character and keeps track of the line and column
+struct Reader<'a> {
+    text: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Reader<'a> {
+    const fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Reads characters for as long as they match
+    fn take_while(&mut self, mut f: impl FnMut(char) -> bool) -> &'a str {
+        let start = self.offset;
+        while self.peek().is_some_and(&mut f) {
+            self.bump();
+        }
+        &self.text[start..self.offset]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    /// Skips the rest of the line including the line break
+    fn skip_line(&mut self) {
+        self.take_while(|c| c != '\n');
+        self.bump();
+    }
+
+    /// An error at a line and column
+    const fn error(line: usize, column: usize, kind: PgnErrorKind) -> PgnError {
+        PgnError { line, column, kind }
+    }
+
+    /// Reads a tag pair like `[Event "Casual game"]` after the `[`.
+    /// The value may escape quotes and backslashes with a backslash
+    fn tag(&mut self) -> Option<(String, String)> {
+        self.take_while(|c| c == ' ' || c == '\t');
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        self.take_while(|c| c == ' ' || c == '\t');
+        if name.is_empty() || self.bump() != Some('"') {
+            return None;
+        }
+        let mut value = String::new();
+        // A broken tag pair doesn't take the line after it with it
+        loop {
+            let c = self.peek().filter(|&c| c != '\n')?;
+            self.bump();
+            match c {
+                '"' => break,
+                '\\' => {
+                    value.push(self.peek().filter(|&c| c != '\n')?);
+                    self.bump();
+                }
+                c => value.push(c),
+            }
+        }
+        self.take_while(|c| c == ' ' || c == '\t');
+        (self.bump() == Some(']')).then(|| (name.to_string(), value))
+    }
+
+    /// Skips what is left of a broken tag pair, up to its `]` or the end of the line
+    fn skip_tag(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            self.bump();
+            if c == ']' {
+                break;
+            }
+        }
+    }
+}
+
+/// Reads a line holding a single tag pair like `[White "Carlsen"]`,
+/// for going through a PGN without reading its games
+#[must_use]
+pub fn read_tag(line: &str) -> Option<(String, String)> {
+    let mut reader = Reader::new(line.trim());
+    if reader.bump() != Some('[') {
+        return None;
+    }
+    reader.tag()
+}
+
+/// Builds the game while going through the movetext
+struct Builder {
+    position: Position,
+    errors: Vec<PgnError>,
+    /// The node to go back to after each open variation
+    stack: Vec<NodeId>,
+    /// No move was played yet in the current line
+    line_start: bool,
+    /// A comment in front of the first move of a variation, waiting for that move
+    before: Option<(String, Vec<Shape>)>,
+    /// Set while skipping the rest of a variation after a bad move,
+    /// counting the variations opened inside the skipped part
+    skip: Option<usize>,
+}
+
+impl Builder {
+    const fn new(position: Position) -> Self {
+        Self {
+            position,
+            errors: Vec::new(),
+            stack: Vec::new(),
+            line_start: true,
+            before: None,
+            skip: None,
+        }
+    }
+
+    fn error(&mut self, line: usize, column: usize, kind: PgnErrorKind) {
+        self.errors.push(Reader::error(line, column, kind));
+    }
+
+    /// Reads moves, comments and variations until the result, the next game or the end
+    fn movetext(&mut self, reader: &mut Reader) {
+        loop {
+            reader.skip_whitespace();
+            let (line, column) = (reader.line, reader.column);
+            let Some(c) = reader.peek() else {
+                break;
+            };
+            match c {
+                '%' if column == 1 => reader.skip_line(),
+                // The tags of the next game
+                '[' => break,
+                '{' => {
+                    reader.bump();
+                    let text = reader.take_while(|c| c != '}');
+                    if reader.bump().is_none() {
+                        self.error(line, column, PgnErrorKind::UnterminatedComment);
+                    }
+                    self.comment(text);
+                }
+                ';' => {
+                    reader.bump();
+                    let text = reader.take_while(|c| c != '\n');
+                    self.comment(text);
+                }
+                '(' => {
+                    reader.bump();
+                    self.open(line, column);
+                }
+                ')' => {
+                    reader.bump();
+                    self.close(line, column);
+                }
+                '$' => {
+                    reader.bump();
+                    let number = format!("${}", reader.take_while(|c| c.is_ascii_digit()));
+                    match number.parse() {
+                        Ok(nag) => self.nag(nag, line, column),
+                        Err(()) => self.error(line, column, PgnErrorKind::UnexpectedToken(number)),
+                    }
+                }
+                _ => {
+                    let symbol =
+                        reader.take_while(|c| !c.is_whitespace() && !"{}()[];$".contains(c));
+                    if symbol.is_empty() {
+                        reader.bump();
+                        let token = PgnErrorKind::UnexpectedToken(c.to_string());
+                        self.error(line, column, token);
+                    } else if self.symbol(symbol, line, column) {
+                        break;
+                    }
+                }
+            }
+        }
+        if !self.stack.is_empty() {
+            self.error(reader.line, reader.column, PgnErrorKind::UnclosedVariation);
+        }
+    }
+
+    /// Handles a move, a move number, a glyph or a result.
+    /// Returns true if it is the result that ends the game
+    fn symbol(&mut self, symbol: &str, line: usize, column: usize) -> bool {
+        if RESULTS.contains(&symbol) {
+            if !self.stack.is_empty() {
+                return false;
+            }
+            if self.position.header("Result").is_none() {
+                self.position.headers.push(("Result".into(), symbol.into()));
+            }
+            return true;
+        }
+        if self.skip.is_some() {
+            return false;
+        }
+        // Move numbers like `12.` or `12...` may be written right in front of the move
+        let digits = symbol.len()
+            - symbol
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .len();
+        let text = match &symbol[digits..] {
+            rest if digits > 0 && (rest.is_empty() || rest.starts_with('.')) => rest,
+            _ => symbol,
+        }
+        .trim_start_matches('.');
+        if text.is_empty() {
+            return false;
+        }
+        if let Ok(nag) = text.parse() {
+            self.nag(nag, line, column);
+            return false;
+        }
+        let (san, glyph) = text.split_at(text.trim_end_matches(['!', '?']).len());
+        match Move::parse_san(&self.position, san) {
+            Ok(r#move) => {
+                self.play(r#move);
+                match glyph.parse() {
+                    Ok(nag) => self.nag(nag, line, column),
+                    Err(()) if glyph.is_empty() => (),
+                    Err(()) => {
+                        let token = PgnErrorKind::UnexpectedToken(glyph.to_string());
+                        self.error(line, column, token);
+                    }
+                }
+            }
+            Err(error) => {
+                self.error(line, column, PgnErrorKind::InvalidMove(error));
+                self.skip = Some(0);
+            }
+        }
+        false
+    }
+
+    /// Plays a move of the current line and gives it the comment waiting in front of it
+    fn play(&mut self, r#move: Move) {
+        self.position.apply_move(r#move);
+        let id = self.position.cursor;
+        if let Some((text, shapes)) = self.before.take() {
+            self.append_comment(id, true, &text);
+            for shape in shapes {
+                self.position.tree.toggle_shape(id, shape);
+            }
+        }
+        self.line_start = false;
+    }
+
+    /// Adds a comment to the move before it. A comment before the first move of the game is
+    /// the comment on the game and one before the first move of a variation waits for it
+    fn comment(&mut self, raw: &str) {
+        if self.skip.is_some() {
+            return;
+        }
+        let (text, shapes) = split_commands(raw);
+        if self.line_start && !self.stack.is_empty() {
+            let before = self.before.get_or_insert_with(Default::default);
+            before.0 = [before.0.as_str(), text.as_str()]
+                .join(" ")
+                .trim()
+                .to_string();
+            before.1.extend(shapes);
+            return;
+        }
+        let id = self.position.cursor;
+        self.append_comment(id, false, &text);
+        for shape in shapes {
+            self.position.tree.toggle_shape(id, shape);
+        }
+    }
+
+    /// Adds text to a comment, after a space if it already has some
+    fn append_comment(&mut self, id: NodeId, before: bool, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let node = self.position.tree.node(id);
+        let old = if before {
+            node.comment_before()
+        } else {
+            node.comment()
+        };
+        let comment = if old.is_empty() {
+            text.to_string()
+        } else {
+            format!("{old} {text}")
+        };
+        self.position.tree.set_comment(id, before, comment);
+    }
+
+    /// Adds a glyph to the last move
+    fn nag(&mut self, nag: Nag, line: usize, column: usize) {
+        if self.skip.is_some() {
+            return;
+        }
+        if self.line_start {
+            self.error(line, column, PgnErrorKind::UnexpectedToken(nag.to_string()));
+        } else {
+            self.position.tree.add_nag(self.position.cursor, nag);
+        }
+    }
+
+    /// Starts a variation that replaces the last move
+    fn open(&mut self, line: usize, column: usize) {
+        if let Some(nested) = &mut self.skip {
+            *nested += 1;
+            return;
+        }
+        let cursor = self.position.cursor;
+        self.stack.push(cursor);
+        let parent = self.position.tree.parent(cursor);
+        if let Some(parent) = parent.filter(|_| !self.line_start) {
+            self.position.go_to(parent);
+            self.line_start = true;
+        } else {
+            self.error(line, column, PgnErrorKind::VariationWithoutMove);
+            self.skip = Some(0);
+        }
+    }
+
+    /// Ends a variation and goes back to the line it is an alternative in
+    fn close(&mut self, line: usize, column: usize) {
+        match &mut self.skip {
+            Some(nested) if *nested > 0 => {
+                *nested -= 1;
+                return;
+            }
+            // Skipping the rest of the mainline
+            Some(_) if self.stack.is_empty() => return,
+            _ => (),
+        }
+        let Some(node) = self.stack.pop() else {
+            self.error(line, column, PgnErrorKind::UnmatchedParenthesis);
+            return;
+        };
+        self.skip = None;
+        self.before = None;
+        self.position.go_to(node);
+        self.line_start = false;
+    }
+}
+
+/// Whether a `Variant` tag names Chess960
+fn is_chess960(name: &str) -> bool {
+    ["chess960", "fischerandom"]
+        .iter()
+        .any(|x| x.eq_ignore_ascii_case(name))
+}
+
+/// The variant a `Variant` tag names. Lichess writes the names shown in its interface
+fn parse_variant(name: &str) -> Option<Variant> {
+    if is_chess960(name)
+        || ["from position", "normal"]
+            .iter()
+            .any(|x| x.eq_ignore_ascii_case(name))
+    {
+        return Some(Variant::Standard);
+    }
+    Variant::ALL.into_iter().find(|variant| {
+        variant.to_string().eq_ignore_ascii_case(name) || variant.uci_name() == name
+    })
+}
+
+/// Reads the first game of a PGN and carries on past problems where it can.
+///
+/// A move that can't be played ends its variation, so the game keeps every move before it.
+/// Text after the game's result or before the tags of the next game is left for the next call,
+/// starting at [`PgnGame::end`]
+#[must_use]
+pub fn read_pgn(pgn: &str) -> PgnGame {
+    let mut reader = Reader::new(pgn);
+    let mut errors = Vec::new();
+    let mut headers = Vec::new();
+    // Where each tag pair starts
+    let mut locations = Vec::new();
+    loop {
+        reader.skip_whitespace();
+        let (line, column) = (reader.line, reader.column);
+        match reader.peek() {
+            Some('%') if column == 1 => reader.skip_line(),
+            Some('[') => {
+                reader.bump();
+                if let Some(tag) = reader.tag() {
+                    headers.push(tag);
+                    locations.push((line, column));
+                } else {
+                    errors.push(Reader::error(line, column, PgnErrorKind::InvalidTag));
+                    reader.skip_tag();
+                }
+            }
+            _ => break,
+        }
+    }
+    let tag = |name: &str| {
+        let index = headers.iter().position(|(x, _)| x == name)?;
+        let (line, column) = locations[index];
+        Some((headers[index].1.as_str(), line, column))
+    };
+    let variant = match tag("Variant") {
+        Some((name, line, column)) => parse_variant(name).unwrap_or_else(|| {
+            let kind = PgnErrorKind::UnsupportedVariant(name.to_string());
+            errors.push(Reader::error(line, column, kind));
+            Variant::Standard
+        }),
+        None => Variant::Standard,
+    };
+    let position = match tag("FEN") {
+        Some((fen, line, column)) => {
+            Position::try_from_variant(fen, variant).unwrap_or_else(|error| {
+                errors.push(Reader::error(line, column, PgnErrorKind::InvalidFen(error)));
+                Position::new(variant)
+            })
+        }
+        None => Position::new(variant),
+    };
+    let mut position = position;
+    // A Chess960 setup can look like a normal one, so the tag says which rules castling follows
+    if tag("Variant").is_some_and(|(name, _, _)| is_chess960(name)) {
+        position.chess960 = true;
+    }
+    position.headers = headers;
+    let mut builder = Builder::new(position);
+    builder.errors = errors;
+    builder.movetext(&mut reader);
+    let mut position = builder.position;
+    position.go_to(GameTree::ROOT);
+    PgnGame {
+        position,
+        errors: builder.errors,
+        end: reader.offset,
+    }
+}
+
+impl Position {
+    /// Reads the first game of a PGN, see [`read_pgn`]
+    /// # Errors
+    /// Fails with the first problem in the PGN
+    pub fn from_pgn(pgn: &str) -> Result<Self, PgnError> {
+        let game = read_pgn(pgn);
+        match game.errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(game.position),
+        }
+    }
+
+    /// The tag pairs of the game, like `Event` and `White`, in their order in the PGN
+    #[must_use]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// The value of a tag pair, if the game has it
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Changes the value of a tag pair, or adds it after the others.
+    /// Tag pairs aren't part of the undo history
+    pub fn set_header(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.headers.iter_mut().find(|(x, _)| x == name) {
+            Some(header) => header.1 = value,
+            None => self.headers.push((name.to_string(), value)),
+        }
+    }
+}
+
+/// Writes a tag pair, escaping quotes and backslashes in the value
+fn write_tag(pgn: &mut String, name: &str, value: &str) {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    // Writing to a string can't fail
+    let _ = writeln!(pgn, "[{name} \"{value}\"]");
+}
+
+/// Joins words into lines of at most [`LINE_WIDTH`] characters.
+/// A longer word gets a line of its own
+fn wrap(words: &[String]) -> String {
+    let mut text = String::new();
+    let mut width = 0;
+    for word in words {
+        let len = word.chars().count();
+        if width > 0 && width + 1 + len > LINE_WIDTH {
+            text.push('\n');
+            width = 0;
+        } else if width > 0 {
+            text.push(' ');
+            width += 1;
+        }
+        text.push_str(word);
+        width += len;
+    }
+    text
+}
+
+impl Position {
+    /// Writes the game in the PGN export format: the Seven Tag Roster followed by the other
+    /// tags, `SetUp` and `FEN` if the game doesn't start from the usual position, and the
+    /// movetext with every variation, comment and glyph, wrapped to fit in 80 columns
+    #[must_use]
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        let result = self
+            .header("Result")
+            .filter(|x| RESULTS.contains(x))
+            .unwrap_or("*");
+        for (name, unknown) in SEVEN_TAG_ROSTER {
+            let value = if name == "Result" {
+                result
+            } else {
+                self.header(name).unwrap_or(unknown)
+            };
+            write_tag(&mut pgn, name, value);
+        }
+        for (name, value) in &self.headers {
+            if !SEVEN_TAG_ROSTER.iter().any(|(x, _)| x == name)
+                && !SETUP_TAGS.contains(&name.as_str())
+            {
+                write_tag(&mut pgn, name, value);
+            }
+        }
+        let variant = self.variant();
+        if self.chess960 {
+            write_tag(&mut pgn, "Variant", "Chess960");
+        } else if variant != Variant::Standard {
+            write_tag(&mut pgn, "Variant", &variant.to_string());
+        }
+        let standard = Self::new(variant).to_fen();
+        let start =
+            Self::try_from_variant(self.starting_position.as_str(), variant).map(|x| x.to_fen());
+        if self.chess960 || start.as_ref() != Ok(&standard) {
+            write_tag(&mut pgn, "SetUp", "1");
+            write_tag(&mut pgn, "FEN", &self.starting_position);
+        }
+        let mut words = Vec::new();
+        let root = self.tree.node(GameTree::ROOT);
+        push_comment(&mut words, root.comment(), root.shapes());
+        // The first move always gets its number, even when black plays it
+        self.write_line(GameTree::ROOT, true, &mut words);
+        words.push(result.to_string());
+        pgn.push('\n');
+        pgn.push_str(&wrap(&words));
+        pgn.push('\n');
+        pgn
+    }
+
+    /// Writes the line after `parent` with its variations in parentheses.
+    /// The first move gets its number even when black plays it if `numbered` is set
+    fn write_line(&self, mut parent: NodeId, mut numbered: bool, words: &mut Vec<String>) {
+        while let Some((&next, variations)) = self.tree.children(parent).split_first() {
+            numbered = self.write_move(next, numbered, words);
+            for &variation in variations {
+                let start = words.len();
+                let commented = self.write_move(variation, true, words);
+                words[start].insert(0, '(');
+                self.write_line(variation, commented, words);
+                if let Some(last) = words.last_mut() {
+                    last.push(')');
+                }
+                numbered = true;
+            }
+            parent = next;
+        }
+    }
+
+    /// Writes a move with its number, glyphs and comments.
+    /// Returns whether a comment came after it, which means the next move needs its number
+    fn write_move(&self, id: NodeId, numbered: bool, words: &mut Vec<String>) -> bool {
+        let node = self.tree.node(id);
+        let numbered = push_comment(words, node.comment_before(), &[]) || numbered;
+        let (number, white) = self.tree.move_number(id);
+        if white {
+            words.push(format!("{number}."));
+        } else if numbered {
+            words.push(format!("{number}..."));
+        }
+        words.push(self.tree.san(id).unwrap_or_default().to_string());
+        words.extend(node.nags().iter().map(|nag| format!("${}", nag.0)));
+        push_comment(words, node.comment(), node.shapes())
+    }
+}
+
+/// Writes a comment in braces, with the commands for the shapes in front.
+/// Returns false if there is nothing to write
+fn push_comment(words: &mut Vec<String>, comment: &str, shapes: &[Shape]) -> bool {
+    let commands = write_commands(shapes);
+    // A comment can't contain the brace that closes it
+    let comment = comment.replace('}', "");
+    // Each command stays on one line
+    let mut comment_words = commands
+        .split_inclusive(']')
+        .map(str::trim)
+        .chain(comment.split_whitespace())
+        .filter(|x| !x.is_empty());
+    let Some(first) = comment_words.next() else {
+        return false;
+    };
+    words.push(format!("{{{first}"));
+    words.extend(comment_words.map(str::to_string));
+    if let Some(last) = words.last_mut() {
+        last.push('}');
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_pgn, PgnErrorKind};
+    use crate::position::{
+        testing::{play, sans},
+        GameTree, MoveParseError, Nag, Position, Shape, ShapeColor, Square,
+    };
+
+    #[test]
+    fn full_game() {
+        let pgn = r#"[Event "Casual \"blitz\""]
+[Site "?"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+{Opening comment} 1. e4 $1 e5!? 2. Nf3 (2. f4 {The King's Gambit} exf4 (2... d5) 3. Nf3)
+2... Nc6 {[%csl Gd4][%cal Rf3e5] Defending} 3.Bb5 ; the Spanish
+3...a6 4. Ba4 +/- 1-0
+"#;
+        let pos = Position::from_pgn(pgn).unwrap();
+        assert_eq!(pos.header("Event"), Some("Casual \"blitz\""));
+        assert_eq!(pos.headers().len(), 5);
+        assert_eq!(pos.cursor(), GameTree::ROOT);
+        let tree = pos.tree();
+        assert_eq!(tree.node(GameTree::ROOT).comment(), "Opening comment");
+        let mainline = tree.mainline();
+        assert_eq!(
+            sans(&pos, &mainline),
+            ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4"]
+        );
+        assert_eq!(tree.node(mainline[0]).nags(), [Nag::GOOD]);
+        assert_eq!(tree.node(mainline[1]).nags(), [Nag::INTERESTING]);
+        assert_eq!(tree.node(mainline[6]).nags(), [Nag(16)]);
+        assert_eq!(tree.node(mainline[3]).comment(), "Defending");
+        assert_eq!(
+            tree.node(mainline[3]).shapes(),
+            [
+                Shape::square(ShapeColor::Green, Square::new(3, 3)),
+                Shape {
+                    color: ShapeColor::Red,
+                    from: Square::new(2, 5),
+                    to: Square::new(4, 4),
+                }
+            ]
+        );
+        assert_eq!(tree.node(mainline[4]).comment(), "the Spanish");
+
+        // 2. f4 replaces 2. Nf3, and 2... d5 replaces 2... exf4 inside it
+        let gambit = tree.children(mainline[1])[1];
+        assert_eq!(tree.node(gambit).comment(), "The King's Gambit");
+        let exf4 = tree.next(gambit).unwrap();
+        assert_eq!(sans(&pos, tree.children(gambit)), ["exf4", "d5"]);
+        assert_eq!(sans(&pos, tree.children(exf4)), ["Nf3"]);
+    }
+
+    #[test]
+    fn quirks() {
+        // No tags, numbers glued to moves, black's move numbered, comments before variations
+        let pos = Position::from_pgn("1.e4 1...c5 2.Nf3 ( { Or } 2.Nc3) 2...d6 *").unwrap();
+        let tree = pos.tree();
+        let mainline = tree.mainline();
+        assert_eq!(sans(&pos, &mainline), ["e4", "c5", "Nf3", "d6"]);
+        let closed = tree.children(mainline[1])[1];
+        assert_eq!(tree.node(closed).comment_before(), "Or");
+        assert_eq!(pos.header("Result"), Some("*"));
+
+        // Setting up a position, and castling written with zeros
+        let pos = Position::from_pgn(
+            "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. 0-0 Kd7 *",
+        )
+        .unwrap();
+        assert_eq!(pos.starting_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert_eq!(pos.tree().mainline().len(), 2);
+
+        let pos =
+            Position::from_pgn("[Variant \"Crazyhouse\"]\n1. e4 d5 2. exd5 Qxd5 3. P@e4").unwrap();
+        assert_eq!(pos.tree().mainline().len(), 5);
+    }
+
+    #[test]
+    fn recovery() {
+        let pgn = "[Event \"Broken]\n[White \"A\"]\n\n1. e4 e5 (1... c5 2. Ke3 Nc6) 2. Nf3 Nc6\n\
+                   3. Bb5 ) 3... a6 4. Bxc7 Nf6 {unterminated";
+        let game = read_pgn(pgn);
+        let errors = game
+            .errors
+            .iter()
+            .map(|x| (x.line, x.column, x.kind.clone()))
+            .collect::<Vec<_>>();
+        let illegal = |x: &str| PgnErrorKind::InvalidMove(MoveParseError::Illegal(x.into()));
+        assert_eq!(
+            errors,
+            [
+                (1, 1, PgnErrorKind::InvalidTag),
+                (4, 22, illegal("Ke3")),
+                (5, 8, PgnErrorKind::UnmatchedParenthesis),
+                (5, 21, illegal("Bxc7")),
+                (5, 30, PgnErrorKind::UnterminatedComment),
+            ]
+        );
+        assert_eq!(
+            game.errors[1].to_string(),
+            "line 4, column 22: 'Ke3' isn't a legal move"
+        );
+        // Everything before the problems is kept
+        let pos = game.position;
+        assert_eq!(pos.header("White"), Some("A"));
+        let tree = pos.tree();
+        let mainline = tree.mainline();
+        assert_eq!(
+            sans(&pos, &mainline),
+            ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]
+        );
+        assert_eq!(sans(&pos, tree.children(mainline[0])), ["e5", "c5"]);
+        let sicilian = tree.children(mainline[0])[1];
+        assert!(tree.children(sicilian).is_empty());
+        assert!(Position::from_pgn(pgn).is_err());
+    }
+
+    #[test]
+    fn several_games() {
+        let pgn = "[Event \"One\"]\n\n1. e4 1-0\n\n[Event \"Two\"]\n\n1. d4 0-1\n";
+        let first = read_pgn(pgn);
+        assert_eq!(first.position.header("Event"), Some("One"));
+        let second = read_pgn(&pgn[first.end..]);
+        assert_eq!(second.position.header("Event"), Some("Two"));
+        // The result token fills in a missing tag
+        assert_eq!(second.position.header("Result"), Some("0-1"));
+        assert!(second.errors.is_empty());
+    }
+
+    #[test]
+    fn export() {
+        let pgn = "[White \"Anand, \\\"Vishy\\\"\"]\n[ECO \"C60\"]\n\n\
+                   {Start} 1. e4 $1 e5 2. Nf3 (2. f4 {Gambit} exf4 (2... d5)) \
+                   2... Nc6 {[%csl Gd4] Defending} 3. Bb5 *";
+        let pos = Position::from_pgn(pgn).unwrap();
+        assert_eq!(
+            pos.to_pgn(),
+            "[Event \"?\"]\n\
+             [Site \"?\"]\n\
+             [Date \"????.??.??\"]\n\
+             [Round \"?\"]\n\
+             [White \"Anand, \\\"Vishy\\\"\"]\n\
+             [Black \"?\"]\n\
+             [Result \"*\"]\n\
+             [ECO \"C60\"]\n\
+             \n\
+             {Start} 1. e4 $1 e5 2. Nf3 (2. f4 {Gambit} 2... exf4 (2... d5)) 2... Nc6\n\
+             {[%csl Gd4] Defending} 3. Bb5 *\n"
+        );
+        // Reading the export gives the same game back
+        let again = Position::from_pgn(&pos.to_pgn()).unwrap();
+        assert_eq!(again.to_pgn(), pos.to_pgn());
+        assert_eq!(again.header("White"), Some("Anand, \"Vishy\""));
+    }
+
+    #[test]
+    fn export_setup() {
+        let mut pos = Position::try_from("4k3/8/8/8/8/8/4P3/4K3 b - - 0 40").unwrap();
+        for _ in 0..20 {
+            play(&mut pos, &["Kd7", "Kd2", "Ke8", "Ke1"]);
+        }
+        pos.set_header("Result", "1/2-1/2");
+        let pgn = pos.to_pgn();
+        assert!(
+            pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 40\"]\n\n40... Kd7")
+        );
+        assert!(pgn.lines().all(|line| line.chars().count() < 80));
+        assert!(pgn.ends_with(" 1/2-1/2\n"));
+        let again = Position::from_pgn(&pgn).unwrap();
+        assert_eq!(again.tree().mainline().len(), 80);
+        // The usual starting position doesn't need the tags
+        assert!(!Position::default().to_pgn().contains("FEN"));
+        assert!(Position::new(crate::position::Variant::Horde)
+            .to_pgn()
+            .contains("[Variant \"Horde\"]\n\n*"));
+    }
+}
@@ -0,0 +1,433 @@
+//! Standard algebraic notation, and parsing moves written in it or in coordinate notation
+
+use std::{error::Error, fmt};
+
+use super::{
+    board::Board,
+    moves::{Move, MoveKind, Square},
+    PieceType, Position,
+};
+
+/// Why a move couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveParseError {
+    /// The text isn't written in any of the supported notations
+    Invalid(String),
+    /// No legal move matches the text
+    Illegal(String),
+    /// More than one legal move matches the text
+    Ambiguous(String),
+}
+
+impl fmt::Display for MoveParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(x) => write!(f, "'{x}' isn't a move"),
+            Self::Illegal(x) => write!(f, "'{x}' isn't a legal move"),
+            Self::Ambiguous(x) => write!(f, "'{x}' could be more than one move"),
+        }
+    }
+}
+
+impl Error for MoveParseError {}
+
+impl Move {
+    /// Gets the algebraic notation of the move when played in `pos`
+    #[must_use]
+    pub fn get_notation(self, pos: &Position) -> Option<String> {
+        self.notation(&pos.board)
+    }
+
+    /// Gets the notation of the move when played on `board`.
+    /// Returns `None` if there is no piece to move
+    pub(super) fn notation(self, board: &Board) -> Option<String> {
+        let mut notation = String::new();
+        if let MoveKind::Drop(piece_type) = self.kind {
+            notation.push(piece_type.to_char().unwrap_or('P'));
+            notation.push('@');
+            notation.push_str(&self.end.to_string());
+        } else if self.kind == MoveKind::Castle {
+            notation.push_str(if self.end.col > self.start.col {
+                "O-O"
+            } else {
+                "O-O-O"
+            });
+        } else {
+            let piece = board.get(self.start)?;
+            let is_capture = board.get(self.end).is_some() || self.kind == MoveKind::EnPassant;
+            if let Some(c) = piece.piece_type.to_char() {
+                notation.push(c);
+                notation.push_str(&self.disambiguation(board));
+            } else if is_capture {
+                notation.push(self.start.to_chess_square()[0]);
+            }
+            if is_capture {
+                notation.push('x');
+            }
+            notation.push_str(&self.end.to_string());
+            if let Some(c) = self.promotion.and_then(PieceType::to_char) {
+                notation.push('=');
+                notation.push(c);
+            }
+        }
+        let mut next = *board;
+        next.play(self);
+        if next.in_check(next.turn) {
+            notation.push(if next.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        Some(notation)
+    }
+
+    /// The file, rank or square of the starting square needed to tell the move apart
+    /// from moves by other pieces of the same kind to the same square
+    fn disambiguation(self, board: &Board) -> String {
+        let piece = board.get(self.start);
+        let others = board
+            .legal_moves()
+            .into_iter()
+            .filter(|x| x.end == self.end && x.start != self.start && board.get(x.start) == piece)
+            .map(|x| x.start)
+            .collect::<Vec<_>>();
+        let [file, rank] = self.start.to_chess_square();
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|x| x.col != self.start.col) {
+            file.to_string()
+        } else if others.iter().all(|x| x.row != self.start.row) {
+            rank.to_string()
+        } else {
+            self.start.to_string()
+        }
+    }
+}
+
+impl Move {
+    /// Parses a move written in uci, long algebraic or standard algebraic notation
+    /// and finds it among the legal moves of the position
+    /// # Errors
+    /// Fails if the text isn't a move or doesn't match exactly one legal move
+    pub fn parse(pos: &Position, text: &str) -> Result<Self, MoveParseError> {
+        Self::parse_uci(pos, text).or_else(|uci_error| match uci_error {
+            MoveParseError::Invalid(_) => Self::parse_san(pos, text),
+            _ => Err(uci_error),
+        })
+    }
+
+    /// Parses a move in the coordinate notation used by uci, such as `e2e4`, `e7e8q` or `N@f3`.
+    /// Castling can be written as the king moving to its target square or taking its own rook
+    /// # Errors
+    /// Fails if the text isn't in uci notation or the move isn't legal
+    pub fn parse_uci(pos: &Position, text: &str) -> Result<Self, MoveParseError> {
+        let invalid = || MoveParseError::Invalid(text.to_string());
+        let chars = text.trim().chars().collect::<Vec<_>>();
+        let (start, end, promotion) = match chars[..] {
+            [piece @ ('Q' | 'R' | 'B' | 'N' | 'P'), '@', c, d] => {
+                let end = parse_square(c, d).ok_or_else(invalid)?;
+                return find_drop(pos, PieceType::from(piece).ok_or_else(invalid)?, end, text);
+            }
+            [a, b, c, d] => (parse_square(a, b), parse_square(c, d), None),
+            [a, b, c, d, promotion] => (
+                parse_square(a, b),
+                parse_square(c, d),
+                Some(PieceType::from(promotion).ok_or_else(invalid)?),
+            ),
+            _ => return Err(invalid()),
+        };
+        let (Some(start), Some(end)) = (start, end) else {
+            return Err(invalid());
+        };
+        pos.board
+            .legal_moves()
+            .into_iter()
+            .find(|x| x.connects(start, end) && x.promotion == promotion)
+            .ok_or_else(|| MoveParseError::Illegal(text.to_string()))
+    }
+
+    /// Parses a move in standard algebraic notation such as `Nf3`, `exd5`, `e8=Q+` or `N@f3`.
+    /// Long algebraic notation like `Ng1-f3` and sloppy forms like `Nbd2` when `Nd2` is
+    /// enough, `0-0`, or `e8Q` are accepted too. Check marks and annotations are ignored
+    /// # Errors
+    /// Fails if the text isn't a move or doesn't match exactly one legal move
+    pub fn parse_san(pos: &Position, text: &str) -> Result<Self, MoveParseError> {
+        let invalid = || MoveParseError::Invalid(text.to_string());
+        let trimmed = text
+            .trim()
+            .trim_end_matches("e.p.")
+            .trim_end_matches(['+', '#', '!', '?']);
+        let legal = pos.board.legal_moves();
+        let candidates = if let Some(kingside) = parse_castling(trimmed) {
+            legal
+                .into_iter()
+                .filter(|x| x.kind == MoveKind::Castle && (x.end.col > x.start.col) == kingside)
+                .collect::<Vec<_>>()
+        } else {
+            let mut chars = trimmed.chars().collect::<Vec<_>>();
+            let piece_type = match chars.first() {
+                Some(&c @ ('K' | 'Q' | 'R' | 'B' | 'N' | 'P')) => {
+                    chars.remove(0);
+                    PieceType::from(c).ok_or_else(invalid)?
+                }
+                _ => PieceType::Pawn,
+            };
+            if let ['@', file, rank] = chars[..] {
+                let end = parse_square(file, rank).ok_or_else(invalid)?;
+                return find_drop(pos, piece_type, end, text);
+            }
+            // The promotion is either written as `=Q` or just `Q` after the square
+            let promotion = match chars[..] {
+                [.., '=', c]
+                | [.., '1'..='8', c @ ('Q' | 'R' | 'B' | 'N' | 'q' | 'r' | 'b' | 'n')] => {
+                    chars.truncate(chars.len() - 1);
+                    if chars.last() == Some(&'=') {
+                        chars.pop();
+                    }
+                    Some(PieceType::from(c).ok_or_else(invalid)?)
+                }
+                _ => None,
+            };
+            let [ref from @ .., file, rank] = chars[..] else {
+                return Err(invalid());
+            };
+            let end = parse_square(file, rank).ok_or_else(invalid)?;
+            // Whatever is left is an optional starting file and rank followed by x or -
+            let from = if let [rest @ .., 'x' | '-'] = from {
+                rest
+            } else {
+                from
+            };
+            let (from_file, from_rank) = match *from {
+                [] => (None, None),
+                [c @ 'a'..='h'] => (Some(c as u8 - b'a'), None),
+                [c @ '1'..='8'] => (None, Some(c as u8 - b'1')),
+                [a, b] => {
+                    let square = parse_square(a, b).ok_or_else(invalid)?;
+                    (Some(square.col), Some(square.row))
+                }
+                _ => return Err(invalid()),
+            };
+            // SAN writes castling as O-O, so the king moving onto its target square only
+            // castles in long algebraic notation, or in Chess960 where it may take its rook
+            let king_castles = pos.is_chess960() || (from_file.is_some() && from_rank.is_some());
+            legal
+                .into_iter()
+                .filter(|x| {
+                    x.connects(x.start, end)
+                        && (x.kind != MoveKind::Castle || king_castles)
+                        && x.promotion == promotion
+                        && pos.board.get(x.start).map(|x| x.piece_type) == Some(piece_type)
+                        && from_file.is_none_or(|col| x.start.col == col)
+                        && from_rank.is_none_or(|row| x.start.row == row)
+                })
+                .collect()
+        };
+        match candidates[..] {
+            [r#move] => Ok(r#move),
+            [] => Err(MoveParseError::Illegal(text.to_string())),
+            _ => Err(MoveParseError::Ambiguous(text.to_string())),
+        }
+    }
+}
+
+/// Finds the legal drop of a piece from the pocket onto `end`
+fn find_drop(
+    pos: &Position,
+    piece_type: PieceType,
+    end: Square,
+    text: &str,
+) -> Result<Move, MoveParseError> {
+    let r#move = Move::drop_piece(piece_type, end);
+    pos.board
+        .legal_moves()
+        .contains(&r#move)
+        .then_some(r#move)
+        .ok_or_else(|| MoveParseError::Illegal(text.to_string()))
+}
+
+/// The square with coordinates like `e` and `4`, if they are on the board
+pub(super) fn parse_square(file: char, rank: char) -> Option<Square> {
+    (('a'..='h').contains(&file) && ('1'..='8').contains(&rank))
+        .then(|| Square::from_chess_square([file, rank]))
+}
+
+/// Returns whether the castling is kingside, or `None` if the text isn't castling
+fn parse_castling(text: &str) -> Option<bool> {
+    match text.replace('0', "O").as_str() {
+        "O-O" => Some(true),
+        "O-O-O" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::MoveParseError;
+    use crate::position::{Move, MoveKind, PieceType, Position, Square};
+
+    /// The notation of the legal move between two squares, which must exist
+    fn notation(fen: &str, start: &str, end: &str) -> String {
+        let square = |x: &str| {
+            let chars = x.chars().collect::<Vec<_>>();
+            Square::from_chess_square([chars[0], chars[1]])
+        };
+        let pos = Position::try_from(fen).unwrap();
+        let moves = pos.moves_between(square(start), square(end));
+        let Some(r#move) = moves.first() else {
+            panic!("{start}{end} isn't legal in {fen}");
+        };
+        r#move.get_notation(&pos).unwrap()
+    }
+
+    #[test]
+    fn pieces_and_pawns() {
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        assert_eq!(notation(fen, "e4", "d5"), "exd5");
+        assert_eq!(notation(fen, "e4", "e5"), "e5");
+        assert_eq!(notation(fen, "g1", "f3"), "Nf3");
+        assert_eq!(notation(fen, "f1", "b5"), "Bb5+");
+        let en_passant = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2";
+        assert_eq!(notation(en_passant, "e5", "d6"), "exd6");
+    }
+
+    #[test]
+    fn disambiguation() {
+        let fen = "4k3/8/8/8/8/5N2/8/RN2K2R w - - 0 1";
+        assert_eq!(notation(fen, "b1", "d2"), "Nbd2");
+        assert_eq!(notation(fen, "a1", "a5"), "Ra5");
+        // Only the rooks that can reach the square count, not the blocked or pinned ones
+        let blocked = "4k3/8/8/8/8/8/8/R1N1K2R w - - 0 1";
+        assert_eq!(notation(blocked, "h1", "f1"), "Rf1");
+        let pinned = "4r1k1/8/8/8/8/8/4N3/2N1K3 w - - 0 1";
+        assert_eq!(notation(pinned, "c1", "d3"), "Nd3");
+        let files = "4k3/8/8/8/8/8/8/R4RK1 w - - 0 1";
+        assert_eq!(notation(files, "a1", "d1"), "Rad1");
+        let ranks = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(notation(ranks, "a1", "a3"), "R1a3");
+        let queens = "2k5/8/8/8/4Q2Q/8/K7/7Q w - - 0 1";
+        assert_eq!(notation(queens, "h4", "e1"), "Qh4e1");
+        assert_eq!(notation(queens, "h1", "e1"), "Q1e1");
+        assert_eq!(notation(queens, "e4", "e1"), "Qee1");
+    }
+
+    #[test]
+    fn castling_promotion_and_mate() {
+        let fen = "r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(notation(fen, "e1", "g1"), "O-O");
+        assert_eq!(notation(fen, "e1", "c1"), "O-O-O");
+        let pos = Position::try_from(fen).unwrap();
+        let promotions = pos.moves_between(Square::new(6, 1), Square::new(7, 0));
+        let promotion = |piece_type| {
+            let r#move = promotions.iter().find(|x| x.promotion == Some(piece_type));
+            r#move.unwrap().get_notation(&pos).unwrap()
+        };
+        assert_eq!(promotion(PieceType::Queen), "bxa8=Q+");
+        assert_eq!(promotion(PieceType::Knight), "bxa8=N");
+        let mate = "rnbqkbnr/ppppp2p/5p2/6p1/4P3/8/PPPP1PPP/RNBQKBNR w KQkq g6 0 3";
+        assert_eq!(notation(mate, "d1", "h5"), "Qh5#");
+    }
+
+    /// Castling and en passant are written from the kind of the move
+    #[test]
+    fn kinds() {
+        let pos = Position::try_from("4k3/8/8/3pP3/8/8/8/R3K2R w KQ d6 0 2").unwrap();
+        let castle = pos.moves_between(Square::new(0, 4), Square::new(0, 6))[0];
+        assert_eq!(castle.kind, MoveKind::Castle);
+        assert_eq!(castle.end, Square::new(0, 7));
+        assert_eq!(castle.get_notation(&pos).unwrap(), "O-O");
+        let en_passant = pos.moves_between(Square::new(4, 4), Square::new(5, 3))[0];
+        assert_eq!(en_passant.kind, MoveKind::EnPassant);
+        assert_eq!(en_passant.get_notation(&pos).unwrap(), "exd6");
+        let king = pos.moves_between(Square::new(0, 4), Square::new(0, 5))[0];
+        assert_eq!(king.kind, MoveKind::Normal);
+        assert_eq!(king.get_notation(&pos).unwrap(), "Kf1");
+    }
+
+    /// Every legal move has a notation that leads back to it through the legal move list
+    #[test]
+    fn round_trip() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "2k5/8/8/8/4Q2Q/8/K7/7Q w - - 0 1",
+        ] {
+            let pos = Position::try_from(fen).unwrap();
+            let moves = pos.get_moves();
+            let notations = moves
+                .iter()
+                .map(|x| x.get_notation(&pos).unwrap())
+                .collect::<HashSet<_>>();
+            assert_eq!(notations.len(), moves.len(), "{fen}");
+            for r#move in &moves {
+                let notation = r#move.get_notation(&pos).unwrap();
+                let found = moves
+                    .iter()
+                    .filter(|x| x.get_notation(&pos).as_ref() == Some(&notation))
+                    .collect::<Vec<_>>();
+                assert_eq!(found, [r#move], "{fen}: {notation}");
+                assert_eq!(Move::parse_san(&pos, &notation), Ok(*r#move), "{notation}");
+                assert_eq!(Move::parse(&pos, &r#move.to_string()), Ok(*r#move));
+            }
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let pos = Position::try_from("r3k2r/1P6/8/6N1/8/8/3P4/R3K2R w KQkq - 0 1").unwrap();
+        let parse = |text| Move::parse(&pos, text).map(|x| x.to_string());
+        assert_eq!(
+            parse("Nd2"),
+            Err(MoveParseError::Illegal("Nd2".to_string()))
+        );
+        assert_eq!(parse("Nf3"), Ok("g5f3".to_string()));
+        assert_eq!(parse("Ngf3"), Ok("g5f3".to_string()));
+        assert_eq!(parse("Ng5-f3"), Ok("g5f3".to_string()));
+        assert_eq!(parse("g5f3"), Ok("g5f3".to_string()));
+        assert_eq!(parse("d4"), Ok("d2d4".to_string()));
+        assert_eq!(parse("d2-d4"), Ok("d2d4".to_string()));
+        assert_eq!(parse("Pd4"), Ok("d2d4".to_string()));
+        assert_eq!(parse("0-0-0"), Ok("e1c1".to_string()));
+        assert_eq!(parse("O-O+"), Ok("e1g1".to_string()));
+        assert_eq!(
+            parse("Kc1"),
+            Err(MoveParseError::Illegal("Kc1".to_string()))
+        );
+        assert_eq!(
+            parse("Kg1"),
+            Err(MoveParseError::Illegal("Kg1".to_string()))
+        );
+        assert_eq!(parse("Ke1-c1"), Ok("e1c1".to_string()));
+        assert_eq!(parse("e1c1"), Ok("e1c1".to_string()));
+        assert_eq!(parse("b8Q"), Ok("b7b8q".to_string()));
+        assert_eq!(parse("bxa8=N"), Ok("b7a8n".to_string()));
+        assert_eq!(parse("b7xa8R!?"), Ok("b7a8r".to_string()));
+        assert_eq!(parse("b7b8b"), Ok("b7b8b".to_string()));
+        assert_eq!(parse("b8"), Err(MoveParseError::Illegal("b8".to_string())));
+        assert_eq!(
+            parse("Zf3"),
+            Err(MoveParseError::Invalid("Zf3".to_string()))
+        );
+        assert_eq!(
+            parse("e2e5"),
+            Err(MoveParseError::Illegal("e2e5".to_string()))
+        );
+
+        let pos = Position::try_from("4k3/8/8/8/8/8/8/RN2KN1R w - - 0 1").unwrap();
+        assert!(matches!(
+            Move::parse(&pos, "Nd2"),
+            Err(MoveParseError::Ambiguous(_))
+        ));
+        let pos =
+            Position::try_from("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3")
+                .unwrap();
+        assert_eq!(
+            Move::parse(&pos, "exf6e.p."),
+            Ok(Move::new(Square::new(4, 4), Square::new(5, 5)).with_kind(MoveKind::EnPassant))
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Arrows and colored squares drawn on the board, stored in comments the way Lichess does:
+//! `[%csl Gd4,Re5]` colors squares and `[%cal Ge2e4]` draws arrows
+
+use super::{moves::Square, san::parse_square, Position};
+
+/// The colors Lichess offers for arrows and squares
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ShapeColor {
+    /// Drawn without a modifier
+    #[default]
+    Green,
+    /// Drawn while holding shift or control
+    Red,
+    /// Drawn while holding alt and shift or control
+    Yellow,
+    /// Drawn while holding alt
+    Blue,
+}
+
+impl ShapeColor {
+    /// The letter of the color in `[%cal]` and `[%csl]` commands
+    #[must_use]
+    pub const fn letter(self) -> char {
+        match self {
+            Self::Green => 'G',
+            Self::Red => 'R',
+            Self::Yellow => 'Y',
+            Self::Blue => 'B',
+        }
+    }
+
+    /// The color with a letter, if it is one of `G`, `R`, `Y` or `B`
+    #[must_use]
+    pub const fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'G' => Some(Self::Green),
+            'R' => Some(Self::Red),
+            'Y' => Some(Self::Yellow),
+            'B' => Some(Self::Blue),
+            _ => None,
+        }
+    }
+
+    /// The color the shape is drawn in
+    #[must_use]
+    pub const fn color(self) -> ratatui::style::Color {
+        match self {
+            Self::Green => ratatui::style::Color::LightGreen,
+            Self::Red => ratatui::style::Color::Red,
+            Self::Yellow => ratatui::style::Color::Yellow,
+            Self::Blue => ratatui::style::Color::Cyan,
+        }
+    }
+}
+
+/// An arrow from one square to another, or a colored square if both are the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shape {
+    /// The color it is drawn in
+    pub color: ShapeColor,
+    /// Where the arrow starts
+    pub from: Square,
+    /// Where the arrow points to
+    pub to: Square,
+}
+
+impl Shape {
+    /// A colored square
+    #[must_use]
+    pub const fn square(color: ShapeColor, square: Square) -> Self {
+        Self {
+            color,
+            from: square,
+            to: square,
+        }
+    }
+
+    /// Whether it colors a square instead of being an arrow
+    #[must_use]
+    pub fn is_square(self) -> bool {
+        self.from == self.to
+    }
+
+    /// Parses a shape like `Gd4` or `Re2e4`
+    fn parse(text: &str) -> Option<Self> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let (color, from, to) = match chars[..] {
+            [color, file, rank] => (color, parse_square(file, rank)?, None),
+            [color, file, rank, to_file, to_rank] => (
+                color,
+                parse_square(file, rank)?,
+                Some(parse_square(to_file, to_rank)?),
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            color: ShapeColor::from_letter(color)?,
+            from,
+            to: to.unwrap_or(from),
+        })
+    }
+}
+
+/// Takes the `[%csl]` and `[%cal]` commands out of a comment.
+/// Returns the rest of the comment and the shapes, skipping ones that can't be read.
+/// Other commands like `[%clk]` stay in the comment
+#[must_use]
+pub fn split_commands(comment: &str) -> (String, Vec<Shape>) {
+    let mut text = String::new();
+    let mut shapes = Vec::new();
+    let mut rest = comment;
+    while let Some(start) = rest.find("[%") {
+        let Some(end) = rest[start..].find(']').map(|x| start + x) else {
+            break;
+        };
+        let command = &rest[start + 2..end];
+        match command.split_once(char::is_whitespace) {
+            Some(("csl" | "cal", list)) => {
+                text.push_str(&rest[..start]);
+                shapes.extend(list.split(',').filter_map(|x| Shape::parse(x.trim())));
+            }
+            _ => text.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    (
+        text.split_whitespace().collect::<Vec<_>>().join(" "),
+        shapes,
+    )
+}
+
+/// Writes the shapes as a `[%csl]` command for the squares and a `[%cal]` one for the arrows,
+/// leaving out the ones without shapes
+#[must_use]
+pub fn write_commands(shapes: &[Shape]) -> String {
+    let list = |squares: bool| {
+        shapes
+            .iter()
+            .filter(|x| x.is_square() == squares)
+            .map(|x| {
+                if squares {
+                    format!("{}{}", x.color.letter(), x.from)
+                } else {
+                    format!("{}{}{}", x.color.letter(), x.from, x.to)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    [("csl", list(true)), ("cal", list(false))]
+        .into_iter()
+        .filter(|(_, list)| !list.is_empty())
+        .map(|(command, list)| format!("[%{command} {list}]"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Position {
+    /// The arrows and colored squares of the current move
+    #[must_use]
+    pub fn shapes(&self) -> &[Shape] {
+        self.tree.node(self.cursor).shapes()
+    }
+
+    /// Draws a shape on the current move like Lichess does: drawing it again removes it
+    /// and drawing it in another color changes the color
+    pub fn toggle_shape(&mut self, shape: Shape) {
+        let id = self.cursor;
+        self.annotate(id, |tree| tree.toggle_shape(id, shape));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{split_commands, write_commands, Shape, ShapeColor};
+    use crate::position::{Position, Square};
+
+    #[test]
+    fn commands() {
+        let d4 = Square::new(3, 3);
+        let e2 = Square::new(1, 4);
+        let e4 = Square::new(3, 4);
+        let (text, shapes) =
+            split_commands("Good [%csl Gd4,Rx9] idea [%cal Ye2e4] [%clk 0:05:00] here");
+        assert_eq!(text, "Good idea [%clk 0:05:00] here");
+        assert_eq!(
+            shapes,
+            [
+                Shape::square(ShapeColor::Green, d4),
+                Shape {
+                    color: ShapeColor::Yellow,
+                    from: e2,
+                    to: e4,
+                }
+            ]
+        );
+        assert_eq!(write_commands(&shapes), "[%csl Gd4] [%cal Ye2e4]");
+        assert_eq!(write_commands(&shapes[1..]), "[%cal Ye2e4]");
+        assert_eq!(write_commands(&[]), "");
+        assert_eq!(split_commands("[%cal Gb1c3]").0, "");
+    }
+
+    #[test]
+    fn toggling() {
+        let mut pos = Position::default();
+        let d4 = Square::new(3, 3);
+        pos.toggle_shape(Shape::square(ShapeColor::Green, d4));
+        pos.toggle_shape(Shape::square(ShapeColor::Red, d4));
+        assert_eq!(pos.shapes(), [Shape::square(ShapeColor::Red, d4)]);
+        pos.toggle_shape(Shape::square(ShapeColor::Red, d4));
+        assert!(pos.shapes().is_empty());
+        assert!(pos.undo());
+        assert_eq!(pos.shapes().len(), 1);
+    }
+}
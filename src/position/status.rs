@@ -0,0 +1,277 @@
+//! Detects when the game is over and how it ended
+
+use std::fmt;
+
+use super::{bitboard::Bitboard, board::Board, Color, PieceType, Position};
+
+/// The outcome of a finished game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    /// 1-0
+    WhiteWins,
+    /// 0-1
+    BlackWins,
+    /// 1/2-1/2
+    Draw,
+}
+
+impl fmt::Display for GameResult {
+    /// Writes the result the way it is written in a pgn
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+        })
+    }
+}
+
+/// Whether the game is still going on, and if not, why it ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameStatus {
+    /// The side to move has legal moves and nothing ended the game
+    Ongoing,
+    /// The side to move is in check without legal moves
+    Checkmate {
+        /// The side that delivered mate
+        winner: Color,
+    },
+    /// The side to move isn't in check, but has no legal moves
+    Stalemate,
+    /// The position occurred for the third time
+    ThreefoldRepetition,
+    /// The position occurred for the fifth time
+    FivefoldRepetition,
+    /// 50 moves by each side without a capture or pawn move
+    FiftyMoveRule,
+    /// 75 moves by each side without a capture or pawn move
+    SeventyFiveMoveRule,
+    /// Neither side has the material left to checkmate
+    InsufficientMaterial,
+    /// King of the Hill: a king reached one of the four center squares
+    KingInTheCenter {
+        /// The side whose king got there
+        winner: Color,
+    },
+    /// Three-check: a side gave its third check
+    ThirdCheck {
+        /// The side that gave the checks
+        winner: Color,
+    },
+    /// Atomic: a king was caught in an explosion
+    KingExploded {
+        /// The side whose king is still on the board
+        winner: Color,
+    },
+    /// Antichess: a side lost all its pieces
+    NoPiecesLeft {
+        /// The side without pieces
+        winner: Color,
+    },
+    /// Antichess: the side to move has no legal moves, which wins
+    NoMovesLeft {
+        /// The side to move
+        winner: Color,
+    },
+    /// Horde: black captured every piece of white
+    HordeDestroyed,
+}
+
+impl GameStatus {
+    /// The result of the game, or `None` if it is still going on.
+    /// Threefold repetition and the fifty move rule normally have to be claimed,
+    /// but are treated as draws here like most chess programs do
+    #[must_use]
+    pub const fn result(self) -> Option<GameResult> {
+        Some(match self {
+            Self::Ongoing => return None,
+            Self::Checkmate { winner }
+            | Self::KingInTheCenter { winner }
+            | Self::ThirdCheck { winner }
+            | Self::KingExploded { winner }
+            | Self::NoPiecesLeft { winner }
+            | Self::NoMovesLeft { winner } => match winner {
+                Color::White => GameResult::WhiteWins,
+                Color::Black => GameResult::BlackWins,
+            },
+            Self::HordeDestroyed => GameResult::BlackWins,
+            Self::Stalemate
+            | Self::ThreefoldRepetition
+            | Self::FivefoldRepetition
+            | Self::FiftyMoveRule
+            | Self::SeventyFiveMoveRule
+            | Self::InsufficientMaterial => GameResult::Draw,
+        })
+    }
+
+    /// Checks whether the game has ended
+    #[must_use]
+    pub const fn is_over(self) -> bool {
+        !matches!(self, Self::Ongoing)
+    }
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ongoing => "Ongoing",
+            Self::Checkmate { .. } => "Checkmate",
+            Self::Stalemate => "Stalemate",
+            Self::ThreefoldRepetition => "Threefold repetition",
+            Self::FivefoldRepetition => "Fivefold repetition",
+            Self::FiftyMoveRule => "Fifty move rule",
+            Self::SeventyFiveMoveRule => "Seventy-five move rule",
+            Self::InsufficientMaterial => "Insufficient material",
+            Self::KingInTheCenter { .. } => "King in the center",
+            Self::ThirdCheck { .. } => "Third check",
+            Self::KingExploded { .. } => "King exploded",
+            Self::NoPiecesLeft { .. } => "No pieces left",
+            Self::NoMovesLeft { .. } => "No moves left",
+            Self::HordeDestroyed => "Horde destroyed",
+        })
+    }
+}
+
+impl Position {
+    /// Finds out whether the game is over.
+    /// A checkmate on the last move counts even if a draw rule also applies
+    #[must_use]
+    pub fn status(&self) -> GameStatus {
+        let rules = self.board.variant.rules();
+        if let Some(status) = rules.outcome(&self.board) {
+            return status;
+        }
+        if self.board.legal_moves().is_empty() {
+            return rules.no_moves(&self.board);
+        }
+        if rules.insufficient_material(&self.board) {
+            return GameStatus::InsufficientMaterial;
+        }
+        let repetitions = self.repetition_count();
+        if repetitions >= 5 {
+            GameStatus::FivefoldRepetition
+        } else if self.halfmove_count >= 150 {
+            GameStatus::SeventyFiveMoveRule
+        } else if repetitions >= 3 {
+            GameStatus::ThreefoldRepetition
+        } else if self.halfmove_count >= 100 {
+            GameStatus::FiftyMoveRule
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+}
+
+impl Board {
+    /// Checks for the positions where no sequence of moves can lead to mate:
+    /// a lone minor piece against a bare king, or only bishops on squares of one color
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy = self.pieces(PieceType::Pawn)
+            | self.pieces(PieceType::Rook)
+            | self.pieces(PieceType::Queen);
+        if !heavy.is_empty() {
+            return false;
+        }
+        let knights = self.pieces(PieceType::Knight);
+        let bishops = self.pieces(PieceType::Bishop);
+        let minors = (knights | bishops).count();
+        if minors <= 1 {
+            return true;
+        }
+        let light = Bitboard::LIGHT_SQUARES;
+        knights.is_empty() && ((bishops & light).is_empty() || (bishops & !light).is_empty())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GameResult, GameStatus};
+    use crate::position::{Color, Move, Position, Square};
+
+    fn status(fen: &str) -> GameStatus {
+        Position::try_from(fen).unwrap().status()
+    }
+
+    #[test]
+    fn mate_and_stalemate() {
+        let mate = status("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        assert_eq!(
+            mate,
+            GameStatus::Checkmate {
+                winner: Color::Black
+            }
+        );
+        assert_eq!(mate.result(), Some(GameResult::BlackWins));
+        assert_eq!(
+            status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),
+            GameStatus::Stalemate
+        );
+        // Mate on the move that reaches the fifty move limit still wins
+        assert_eq!(status("7k/5Q2/8/6K1/8/8/8/8 w - - 99 80").result(), None);
+        assert_eq!(
+            status("7k/6Q1/6K1/8/8/8/8/8 b - - 100 80"),
+            GameStatus::Checkmate {
+                winner: Color::White
+            }
+        );
+    }
+
+    #[test]
+    fn move_rules() {
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/R3K3 w - - 100 80"),
+            GameStatus::FiftyMoveRule
+        );
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/R3K3 w - - 150 80"),
+            GameStatus::SeventyFiveMoveRule
+        );
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/R3K3 w - - 99 80"),
+            GameStatus::Ongoing
+        );
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        ] {
+            assert_eq!(status(fen), GameStatus::InsufficientMaterial, "{fen}");
+        }
+        for fen in [
+            "4k3/8/8/8/8/8/8/2BBK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3NKN2 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        ] {
+            assert_eq!(status(fen), GameStatus::Ongoing, "{fen}");
+        }
+    }
+
+    #[test]
+    fn repetition() {
+        let mut pos = Position::default();
+        let knight_moves = [
+            Move::new(Square::new(0, 6), Square::new(2, 5)),
+            Move::new(Square::new(7, 6), Square::new(5, 5)),
+            Move::new(Square::new(2, 5), Square::new(0, 6)),
+            Move::new(Square::new(5, 5), Square::new(7, 6)),
+        ];
+        for _ in 0..2 {
+            for r#move in knight_moves {
+                assert_eq!(pos.status(), GameStatus::Ongoing);
+                pos.make_move(r#move);
+            }
+        }
+        assert_eq!(pos.status(), GameStatus::ThreefoldRepetition);
+        for _ in 0..2 {
+            for r#move in knight_moves {
+                pos.make_move(r#move);
+            }
+        }
+        assert_eq!(pos.status(), GameStatus::FivefoldRepetition);
+    }
+}
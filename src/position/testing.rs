@@ -0,0 +1,17 @@
+//! Shortcuts shared by the tests of the position modules
+
+use super::{Move, NodeId, Position};
+
+/// Plays moves given in any notation [`Move::parse`] accepts
+pub fn play(pos: &mut Position, moves: &[&str]) {
+    for r#move in moves {
+        pos.make_move(Move::parse(pos, r#move).unwrap());
+    }
+}
+
+/// The san of every node
+pub fn sans(pos: &Position, ids: &[NodeId]) -> Vec<String> {
+    ids.iter()
+        .map(|&id| pos.tree().san(id).unwrap().to_string())
+        .collect()
+}
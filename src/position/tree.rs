@@ -0,0 +1,818 @@
+//! The tree of moves of a game, with the mainline and every variation
+
+use super::{annotation::Nag, moves::Move, shapes::Shape, Color, Position, State};
+
+/// Refers to a node in a [`GameTree`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// A position in the game and the move that led to it
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// `None` for the starting position
+    r#move: Option<Move>,
+    /// The move in standard algebraic notation, worked out once when it is added
+    san: Option<String>,
+    parent: Option<NodeId>,
+    /// The first child continues the line, the others are variations
+    children: Vec<NodeId>,
+    /// The position after the move
+    state: State,
+    annotations: Annotations,
+}
+
+/// What a move is annotated with
+#[derive(Debug, Clone, Default)]
+pub(super) struct Annotations {
+    /// The comment in front of the move
+    comment_before: String,
+    /// The comment after the move. For the root it is the comment before the whole game
+    comment: String,
+    /// Numeric annotation glyphs like `$1` for a good move, sorted
+    nags: Vec<Nag>,
+    /// Arrows and colored squares drawn on the position after the move
+    shapes: Vec<Shape>,
+}
+
+impl Node {
+    /// The move that led to the node, `None` for the starting position
+    #[must_use]
+    pub const fn r#move(&self) -> Option<Move> {
+        self.r#move
+    }
+
+    /// The zobrist key of the position after the move
+    #[must_use]
+    pub const fn key(&self) -> u64 {
+        self.state.board.key()
+    }
+
+    /// The comment in front of the move
+    #[must_use]
+    pub fn comment_before(&self) -> &str {
+        &self.annotations.comment_before
+    }
+
+    /// The comment after the move
+    #[must_use]
+    pub fn comment(&self) -> &str {
+        &self.annotations.comment
+    }
+
+    /// The numeric annotation glyphs of the move, sorted by number
+    #[must_use]
+    pub fn nags(&self) -> &[Nag] {
+        &self.annotations.nags
+    }
+
+    /// The arrows and colored squares drawn on the position after the move
+    #[must_use]
+    pub fn shapes(&self) -> &[Shape] {
+        &self.annotations.shapes
+    }
+
+    /// The comments, glyphs and shapes of the move
+    pub(super) const fn annotations(&self) -> &Annotations {
+        &self.annotations
+    }
+}
+
+/// Every move played or analysed from the starting position.
+/// Deleted moves are only unlinked from their parent, so a [`NodeId`] stays valid
+/// until the game gets a new starting position
+#[derive(Debug, Clone)]
+pub struct GameTree {
+    nodes: Vec<Node>,
+}
+
+impl GameTree {
+    /// The starting position, which every line begins from
+    pub const ROOT: NodeId = NodeId(0);
+
+    /// A tree with only the starting position
+    pub(super) fn new(state: State) -> Self {
+        Self {
+            nodes: vec![Node {
+                r#move: None,
+                san: None,
+                parent: None,
+                children: Vec::new(),
+                state,
+                annotations: Annotations::default(),
+            }],
+        }
+    }
+
+    /// Gets a node
+    #[must_use]
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    /// The board and counters after the move of a node
+    pub(super) fn state(&self, id: NodeId) -> &State {
+        &self.nodes[id.0].state
+    }
+
+    /// The node before, `None` for the root
+    #[must_use]
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    /// The moves that can follow a node. The first one continues the line
+    #[must_use]
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    /// The moves after a node, for changing them
+    pub(super) fn children_mut(&mut self, id: NodeId) -> &mut Vec<NodeId> {
+        &mut self.nodes[id.0].children
+    }
+
+    /// The comments, glyphs and shapes of a node, for changing them
+    pub(super) fn annotations_mut(&mut self, id: NodeId) -> &mut Annotations {
+        &mut self.nodes[id.0].annotations
+    }
+
+    /// The node after this one in the same line
+    #[must_use]
+    pub fn next(&self, id: NodeId) -> Option<NodeId> {
+        self.children(id).first().copied()
+    }
+
+    /// The move of a node in standard algebraic notation, `None` for the root
+    #[must_use]
+    pub fn san(&self, id: NodeId) -> Option<&str> {
+        self.node(id).san.as_deref()
+    }
+
+    /// The number a node's move is written with and whether white played it
+    #[must_use]
+    pub fn move_number(&self, id: NodeId) -> (u64, bool) {
+        let before = self.parent(id).unwrap_or(id);
+        let state = self.state(before);
+        (state.movecount, state.board.turn == Color::White)
+    }
+
+    /// The child of a node that plays a move, if there is one
+    #[must_use]
+    pub fn find_child(&self, id: NodeId, r#move: Move) -> Option<NodeId> {
+        self.children(id)
+            .iter()
+            .copied()
+            .find(|&child| self.node(child).r#move == Some(r#move))
+    }
+
+    /// The nodes from the root to `id`, both included
+    #[must_use]
+    pub fn path(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path = std::iter::successors(Some(id), |&x| self.parent(x)).collect::<Vec<_>>();
+        path.reverse();
+        path
+    }
+
+    /// The nodes of the mainline after the root
+    #[must_use]
+    pub fn mainline(&self) -> Vec<NodeId> {
+        std::iter::successors(self.next(Self::ROOT), |&x| self.next(x)).collect()
+    }
+
+    /// Whether a node is on the mainline, which also holds for the root
+    #[must_use]
+    pub fn is_mainline(&self, id: NodeId) -> bool {
+        self.path(id)
+            .windows(2)
+            .all(|pair| self.next(pair[0]) == Some(pair[1]))
+    }
+
+    /// The first move of the variation a node is in, `None` on the mainline
+    #[must_use]
+    pub fn variation_start(&self, id: NodeId) -> Option<NodeId> {
+        self.path(id)
+            .windows(2)
+            .rev()
+            .find(|pair| self.next(pair[0]) != Some(pair[1]))
+            .map(|pair| pair[1])
+    }
+
+    /// The nearest node of the line, `id` included, that has alternatives
+    fn branch(&self, id: NodeId) -> Option<NodeId> {
+        std::iter::successors(Some(id), |&x| self.parent(x)).find(|&x| {
+            self.parent(x)
+                .is_some_and(|parent| self.children(parent).len() > 1)
+        })
+    }
+
+    /// Makes every move leading to a node the continuation of its line,
+    /// which turns the node's line into the mainline.
+    /// Returns false if it already was the mainline
+    pub(super) fn promote(&mut self, id: NodeId) -> bool {
+        let mut changed = false;
+        for pair in self.path(id).windows(2) {
+            let children = &mut self.nodes[pair[0].0].children;
+            if let Some(index) = children.iter().position(|&x| x == pair[1]) {
+                children[..=index].rotate_right(1);
+                changed |= index > 0;
+            }
+        }
+        changed
+    }
+
+    /// Swaps the line of a node with the alternative before or after it where it branches off.
+    /// Moving the first alternative up makes it the continuation.
+    /// Returns false if there is nothing to swap with
+    pub(super) fn shift(&mut self, id: NodeId, up: bool) -> bool {
+        let Some(branch) = self.branch(id) else {
+            return false;
+        };
+        let Some(parent) = self.parent(branch) else {
+            return false;
+        };
+        let children = &mut self.nodes[parent.0].children;
+        let Some(index) = children.iter().position(|&x| x == branch) else {
+            return false;
+        };
+        let other = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1).filter(|&x| x < children.len())
+        };
+        other.is_some_and(|other| {
+            children.swap(index, other);
+            true
+        })
+    }
+
+    /// Unlinks a node and every move after it from its parent
+    pub(super) fn remove(&mut self, id: NodeId) {
+        if let Some(parent) = self.parent(id) {
+            self.nodes[parent.0].children.retain(|&x| x != id);
+        }
+    }
+
+    /// Unlinks every move after a node
+    pub(super) fn truncate(&mut self, id: NodeId) {
+        self.nodes[id.0].children.clear();
+    }
+
+    /// A new tree with a node as its root and the moves after it.
+    /// The comment after the node's move becomes the comment on the game
+    pub(super) fn subtree(&self, id: NodeId) -> Self {
+        let node = self.node(id);
+        let mut tree = Self::new(node.state);
+        tree.nodes[0]
+            .annotations
+            .comment
+            .clone_from(&node.annotations.comment);
+        let mut queue = vec![(id, Self::ROOT)];
+        while let Some((old, new)) = queue.pop() {
+            for &child in self.children(old) {
+                let id = NodeId(tree.nodes.len());
+                tree.nodes.push(Node {
+                    parent: Some(new),
+                    children: Vec::new(),
+                    ..self.node(child).clone()
+                });
+                tree.nodes[new.0].children.push(id);
+                queue.push((child, id));
+            }
+        }
+        tree
+    }
+
+    /// Replaces the comment before or after a move
+    pub(super) fn set_comment(&mut self, id: NodeId, before: bool, comment: String) {
+        let node = &mut self.nodes[id.0].annotations;
+        if before {
+            node.comment_before = comment;
+        } else {
+            node.comment = comment;
+        }
+    }
+
+    /// Copies the comments, glyphs and shapes of one node to another
+    fn copy_annotations(&mut self, from: NodeId, to: NodeId) {
+        self.nodes[to.0].annotations = self.node(from).annotations.clone();
+    }
+
+    /// Adds a shape to a node, changes the color of the one at the same squares,
+    /// or removes it if it already has that color
+    pub(super) fn toggle_shape(&mut self, id: NodeId, shape: Shape) {
+        let shapes = &mut self.nodes[id.0].annotations.shapes;
+        let same = shapes
+            .iter()
+            .position(|x| x.from == shape.from && x.to == shape.to);
+        match same {
+            Some(index) if shapes[index].color == shape.color => {
+                shapes.remove(index);
+            }
+            Some(index) => shapes[index] = shape,
+            None => shapes.push(shape),
+        }
+    }
+
+    /// Adds a glyph to a move, replacing one that judges the same thing
+    pub(super) fn add_nag(&mut self, id: NodeId, nag: Nag) {
+        let nags = &mut self.nodes[id.0].annotations.nags;
+        nags.retain(|x| !x.conflicts(nag) && *x != nag);
+        nags.push(nag);
+        nags.sort_unstable();
+    }
+
+    /// Removes a glyph from a move
+    pub(super) fn remove_nag(&mut self, id: NodeId, nag: Nag) {
+        self.nodes[id.0].annotations.nags.retain(|&x| x != nag);
+    }
+
+    /// Adds a move after `parent`, or returns the child that already plays it.
+    /// A new move continues the line if `parent` has no children and starts a variation otherwise
+    pub(super) fn add(&mut self, parent: NodeId, r#move: Move, state: State) -> NodeId {
+        if let Some(child) = self.find_child(parent, r#move) {
+            return child;
+        }
+        let id = NodeId(self.nodes.len());
+        let san = r#move.notation(&self.state(parent).board);
+        self.nodes.push(Node {
+            r#move: Some(r#move),
+            san,
+            parent: Some(parent),
+            children: Vec::new(),
+            state,
+            annotations: Annotations::default(),
+        });
+        self.nodes[parent.0].children.push(id);
+        id
+    }
+}
+
+impl Position {
+    /// Every move and variation of the game
+    #[must_use]
+    pub const fn tree(&self) -> &GameTree {
+        &self.tree
+    }
+
+    /// The node of the position on the board
+    #[must_use]
+    pub const fn cursor(&self) -> NodeId {
+        self.cursor
+    }
+
+    /// Shows the position of another node
+    pub fn go_to(&mut self, id: NodeId) {
+        let state = *self.tree.state(id);
+        self.board = state.board;
+        self.halfmove_count = state.halfmove_count;
+        self.movecount = state.movecount;
+        self.cursor = id;
+        self.highlighted = None;
+        self.promotion = None;
+    }
+
+    /// Goes to the next move of the current line.
+    /// Returns false at the end of the line
+    pub fn go_forward(&mut self) -> bool {
+        let Some(next) = self.tree.next(self.cursor) else {
+            return false;
+        };
+        self.go_to(next);
+        true
+    }
+
+    /// Goes to the last move of the current line
+    pub fn go_to_end(&mut self) {
+        while self.go_forward() {}
+    }
+
+    /// Goes to the first move of a variation that branches off at the current position.
+    /// On the first move of a variation this goes to the next variation instead,
+    /// so pressing it repeatedly cycles through the alternatives.
+    /// Returns false if there is no variation to enter
+    pub fn enter_variation(&mut self) -> bool {
+        if let Some(&variation) = self.tree.children(self.cursor).get(1) {
+            self.go_to(variation);
+            return true;
+        }
+        let Some(parent) = self.tree.parent(self.cursor) else {
+            return false;
+        };
+        let siblings = self.tree.children(parent);
+        let index = siblings.iter().position(|&x| x == self.cursor);
+        match index.and_then(|index| siblings.get(index + 1)) {
+            Some(&sibling) => {
+                self.go_to(sibling);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Goes back to the position the current variation branches off from,
+    /// so going forward continues the line it is an alternative to.
+    /// Returns false on the mainline
+    pub fn leave_variation(&mut self) -> bool {
+        let mut node = self.cursor;
+        while let Some(parent) = self.tree.parent(node) {
+            if self.tree.next(parent) != Some(node) {
+                self.go_to(parent);
+                return true;
+            }
+            node = parent;
+        }
+        false
+    }
+
+    /// Replaces the comment before or after a move.
+    /// The comment after the root is the comment on the whole game
+    pub fn set_comment(&mut self, id: NodeId, before: bool, comment: impl Into<String>) {
+        let comment = comment.into();
+        let node = self.tree.node(id);
+        let old = if before {
+            node.comment_before()
+        } else {
+            node.comment()
+        };
+        if old != comment {
+            self.annotate(id, |tree| tree.set_comment(id, before, comment));
+        }
+    }
+
+    /// Adds a glyph to a move, replacing one that judges the same thing,
+    /// e.g. `?` replaces `!`
+    pub fn add_nag(&mut self, id: NodeId, nag: Nag) {
+        if !self.tree.node(id).nags().contains(&nag) {
+            self.annotate(id, |tree| tree.add_nag(id, nag));
+        }
+    }
+
+    /// Removes a glyph from a move
+    pub fn remove_nag(&mut self, id: NodeId, nag: Nag) {
+        if self.tree.node(id).nags().contains(&nag) {
+            self.annotate(id, |tree| tree.remove_nag(id, nag));
+        }
+    }
+
+    /// Turns the line of the current move into the mainline.
+    /// Returns false if it already is
+    pub fn promote_to_mainline(&mut self) -> bool {
+        self.edit(|pos| pos.promote(pos.cursor))
+    }
+
+    /// Promotes the line of a node like [`GameTree::promote`] as part of an edit
+    fn promote(&mut self, id: NodeId) -> bool {
+        for pair in self.tree.path(id).windows(2) {
+            if self.tree.next(pair[0]) != Some(pair[1]) {
+                self.save_children(pair[0]);
+            }
+        }
+        self.tree.promote(id)
+    }
+
+    /// Moves the line of the current move one place up or down among the alternatives
+    /// where it branches off. Moving a variation above the continuation promotes it.
+    /// Returns false if it is already first or last
+    pub fn move_variation(&mut self, up: bool) -> bool {
+        self.edit(|pos| {
+            let parent = pos.tree.branch(pos.cursor).and_then(|x| pos.tree.parent(x));
+            if let Some(parent) = parent {
+                pos.save_children(parent);
+            }
+            pos.tree.shift(pos.cursor, up)
+        })
+    }
+
+    /// Deletes the variation the current move is in, from its first move on,
+    /// and goes to the position it branched off from.
+    /// Returns false on the mainline
+    pub fn delete_variation(&mut self) -> bool {
+        let Some(start) = self.tree.variation_start(self.cursor) else {
+            return false;
+        };
+        let Some(parent) = self.tree.parent(start) else {
+            return false;
+        };
+        self.edit(|pos| {
+            pos.save_children(parent);
+            pos.tree.remove(start);
+            true
+        });
+        self.go_to(parent);
+        true
+    }
+
+    /// Deletes every move after the current position, including the variations
+    pub fn delete_remaining_moves(&mut self) {
+        if !self.tree.children(self.cursor).is_empty() {
+            self.edit(|pos| {
+                pos.save_children(pos.cursor);
+                pos.tree.truncate(pos.cursor);
+                true
+            });
+        }
+    }
+
+    /// Makes the current position the start of the game and deletes the moves before it
+    pub fn delete_previous_moves(&mut self) {
+        if self.cursor == GameTree::ROOT {
+            return;
+        }
+        let tree = self.tree.subtree(self.cursor);
+        self.replace_game(self.to_fen(), tree);
+        self.cursor = GameTree::ROOT;
+        self.displaced = None;
+    }
+
+    /// Plays a move and puts it in front of the continuation instead of starting a variation.
+    /// The moves of the old continuation and its variations are played again after it
+    /// if they are still legal. If its first move isn't, the old line stays as a variation
+    /// and the next move inserted after this one tries again, so a pair of moves can be inserted.
+    /// The old line is removed once all of its moves have been played again,
+    /// so nothing is lost when some of them can't be
+    pub fn insert_move(&mut self, r#move: Move) {
+        let r#move = self.classify(r#move);
+        let parent = self.cursor;
+        let displaced = self
+            .displaced
+            .filter(|&(inserted, _)| inserted == parent)
+            .map(|(_, old)| old);
+        let old = self.tree.next(parent).or(displaced);
+        if self.tree.find_child(parent, r#move).is_some() {
+            self.make_move(r#move);
+            return;
+        }
+        self.edit(|pos| {
+            pos.make_move(r#move);
+            if pos.cursor == parent {
+                return false;
+            }
+            let inserted = pos.cursor;
+            pos.promote(inserted);
+            pos.displaced = None;
+            if let Some(old) = old {
+                match pos.replay(old, inserted) {
+                    (_, true) => {
+                        pos.save_children(parent);
+                        pos.tree.remove(old);
+                    }
+                    (false, _) => pos.displaced = Some((inserted, old)),
+                    (true, false) => (),
+                }
+            }
+            pos.go_to(inserted);
+            true
+        });
+    }
+
+    /// Plays the move of `old` and everything after it, variations included, from `at`.
+    /// A move that isn't legal any more is left out together with what follows it.
+    /// Returns whether the move of `old` could be played and whether all of it was
+    fn replay(&mut self, old: NodeId, at: NodeId) -> (bool, bool) {
+        self.go_to(at);
+        let legal = self.board.legal_moves();
+        let Some(r#move) = self.tree.node(old).r#move.filter(|x| legal.contains(x)) else {
+            return (false, false);
+        };
+        self.apply_move(r#move);
+        let new = self.cursor;
+        self.tree.copy_annotations(old, new);
+        let mut complete = true;
+        for child in self.tree.children(old).to_vec() {
+            complete &= self.replay(child, new).1;
+        }
+        (true, complete)
+    }
+
+    /// Whether moves made on the board are inserted with [`Position::insert_move`]
+    #[must_use]
+    pub const fn inserting(&self) -> bool {
+        self.inserting
+    }
+
+    /// Changes whether moves made on the board are inserted or start a variation
+    pub const fn set_inserting(&mut self, inserting: bool) {
+        self.inserting = inserting;
+    }
+
+    /// The moves from the starting position to the one on the board
+    #[must_use]
+    pub fn moves(&self) -> Vec<Move> {
+        self.tree
+            .path(self.cursor)
+            .into_iter()
+            .filter_map(|id| self.tree.node(id).r#move)
+            .collect()
+    }
+
+    /// The move that led to the position on the board
+    #[must_use]
+    pub fn last_move(&self) -> Option<Move> {
+        self.tree.node(self.cursor).r#move
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GameTree;
+    use crate::position::{
+        testing::{play, sans},
+        Move, Position,
+    };
+
+    #[test]
+    fn variations() {
+        let mut pos = Position::default();
+        play(&mut pos, &["e4", "e5", "Nf3"]);
+        let nf3 = pos.cursor();
+        pos.unmake_move();
+        pos.unmake_move();
+        // Playing the same move again follows the existing line
+        play(&mut pos, &["e5"]);
+        assert_eq!(pos.tree().children(pos.cursor()), &[nf3]);
+        pos.unmake_move();
+        play(&mut pos, &["c5", "Nf3"]);
+        assert_eq!(pos.moves().len(), 3);
+        assert_eq!(
+            pos.to_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        let tree = pos.tree();
+        let e4 = tree.next(GameTree::ROOT).unwrap();
+        assert_eq!(tree.children(e4).len(), 2);
+        assert_eq!(tree.mainline().len(), 3);
+        assert_eq!(tree.mainline()[2], nf3);
+        assert!(tree.is_mainline(nf3));
+        assert!(!tree.is_mainline(pos.cursor()));
+        assert_eq!(tree.path(pos.cursor())[1], e4);
+
+        // The board follows the cursor
+        pos.go_to(nf3);
+        assert_eq!(pos.last_move().unwrap().to_string(), "g1f3");
+        assert_eq!(
+            pos.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        assert_eq!(pos.tree().node(nf3).key(), pos.key());
+        pos.go_to(GameTree::ROOT);
+        assert_eq!(pos.to_fen(), Position::default().to_fen());
+        assert!(pos.last_move().is_none());
+        assert!(pos.unmake_move().is_none());
+    }
+
+    #[test]
+    fn navigation() {
+        let mut pos = Position::default();
+        play(&mut pos, &["e4", "e5", "Nf3", "Nc6"]);
+        let end = pos.cursor();
+        pos.go_to(GameTree::ROOT);
+        play(&mut pos, &["d4", "d5"]);
+        let d5 = pos.cursor();
+        pos.go_to(GameTree::ROOT);
+        play(&mut pos, &["c4"]);
+        let c4 = pos.cursor();
+        pos.go_to(pos.tree().mainline()[0]);
+        play(&mut pos, &["c5", "Nf3"]);
+        let sicilian = pos.cursor();
+
+        pos.go_to(GameTree::ROOT);
+        assert!(pos.go_forward());
+        pos.go_to_end();
+        assert_eq!(pos.cursor(), end);
+        assert!(!pos.go_forward());
+        assert!(!pos.leave_variation());
+
+        // From the start the variations are 1. d4 and then 1. c4
+        pos.go_to(GameTree::ROOT);
+        assert!(pos.enter_variation());
+        pos.go_to_end();
+        assert_eq!(pos.cursor(), d5);
+        // Entering again from the first move of a variation goes to the next one
+        pos.go_to(pos.tree().parent(d5).unwrap());
+        assert!(pos.enter_variation());
+        assert_eq!(pos.cursor(), c4);
+        assert!(!pos.enter_variation());
+        assert!(pos.leave_variation());
+        assert_eq!(pos.cursor(), GameTree::ROOT);
+
+        // Leaving a variation deep inside goes back to where it branched off
+        pos.go_to(sicilian);
+        assert!(pos.leave_variation());
+        assert_eq!(pos.cursor(), pos.tree().mainline()[0]);
+        assert!(pos.go_forward());
+        assert_eq!(pos.cursor(), pos.tree().mainline()[1]);
+    }
+
+    #[test]
+    fn editing() {
+        let mut pos = Position::default();
+        play(&mut pos, &["e4", "e5", "Nf3"]);
+        pos.go_to(pos.tree().mainline()[0]);
+        play(&mut pos, &["c5", "Nf3"]);
+        let sicilian = pos.cursor();
+        pos.go_to(pos.tree().mainline()[0]);
+        play(&mut pos, &["e6"]);
+        let french = pos.cursor();
+
+        // The French moves up past the Sicilian and then becomes the continuation
+        assert_eq!(pos.tree().variation_start(french), Some(french));
+        assert!(pos.move_variation(true));
+        assert!(pos.move_variation(true));
+        assert!(!pos.move_variation(true));
+        assert_eq!(sans(&pos, &pos.tree().mainline()), ["e4", "e6"]);
+        assert!(pos.move_variation(false));
+        assert_eq!(sans(&pos, &pos.tree().mainline()), ["e4", "e5", "Nf3"]);
+
+        pos.go_to(sicilian);
+        assert!(pos.promote_to_mainline());
+        assert!(!pos.promote_to_mainline());
+        assert_eq!(sans(&pos, &pos.tree().mainline()), ["e4", "c5", "Nf3"]);
+        assert_eq!(pos.tree().variation_start(sicilian), None);
+
+        // Deleting the French goes back to where it branched off
+        pos.go_to(french);
+        assert!(pos.delete_variation());
+        assert_eq!(pos.cursor(), pos.tree().mainline()[0]);
+        assert_eq!(pos.tree().children(pos.cursor()).len(), 2);
+        assert!(!pos.delete_variation());
+
+        pos.delete_remaining_moves();
+        assert_eq!(pos.tree().mainline().len(), 1);
+        play(&mut pos, &["c5", "Nf3", "d6"]);
+        pos.go_to(pos.tree().mainline()[1]);
+        pos.set_comment(pos.cursor(), false, "Sicilian");
+        pos.delete_previous_moves();
+        assert_eq!(pos.cursor(), GameTree::ROOT);
+        assert_eq!(
+            pos.starting_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2"
+        );
+        assert_eq!(pos.tree().node(GameTree::ROOT).comment(), "Sicilian");
+        assert_eq!(sans(&pos, &pos.tree().mainline()), ["Nf3", "d6"]);
+    }
+
+    #[test]
+    fn inserting() {
+        let mut pos = Position::default();
+        play(&mut pos, &["d4", "d5", "c4"]);
+        let c4 = pos.cursor();
+        pos.set_comment(c4, false, "The Queen's Gambit");
+        pos.go_to(GameTree::ROOT);
+        pos.insert_move(Move::parse(&pos, "Nf3").unwrap());
+        // Black would have to move next, so 1. d4 can only follow another inserted move
+        assert_eq!(pos.tree().children(GameTree::ROOT).len(), 2);
+        pos.insert_move(Move::parse(&pos, "Nf6").unwrap());
+        let mainline = pos.tree().mainline();
+        assert_eq!(sans(&pos, &mainline), ["Nf3", "Nf6", "d4", "d5", "c4"]);
+        assert_eq!(pos.tree().children(GameTree::ROOT).len(), 1);
+        assert_eq!(pos.cursor(), mainline[1]);
+        assert_eq!(pos.tree().node(mainline[4]).comment(), "The Queen's Gambit");
+
+        // The old line is kept when only some of its moves are still legal
+        pos.go_to(GameTree::ROOT);
+        pos.insert_move(Move::parse(&pos, "d3").unwrap());
+        pos.insert_move(Move::parse(&pos, "e5").unwrap());
+        assert_eq!(
+            sans(&pos, &pos.tree().mainline()),
+            ["d3", "e5", "Nf3", "Nf6"]
+        );
+        assert_eq!(pos.tree().children(GameTree::ROOT).len(), 2);
+    }
+
+    #[test]
+    fn inserting_keeps_variations() {
+        let mut pos = Position::default();
+        play(&mut pos, &["d4", "d5", "c4"]);
+        pos.unmake_move();
+        play(&mut pos, &["Bf4"]);
+        pos.set_comment(pos.cursor(), false, "The London");
+        pos.go_to(GameTree::ROOT);
+        pos.insert_move(Move::parse(&pos, "Nf3").unwrap());
+        pos.insert_move(Move::parse(&pos, "Nf6").unwrap());
+        let tree = pos.tree();
+        let mainline = tree.mainline();
+        assert_eq!(sans(&pos, &mainline), ["Nf3", "Nf6", "d4", "d5", "c4"]);
+        assert_eq!(tree.children(GameTree::ROOT).len(), 1);
+        let sideline = tree.children(mainline[3]).to_vec();
+        assert_eq!(sans(&pos, &sideline), ["c4", "Bf4"]);
+        assert_eq!(tree.node(sideline[1]).comment(), "The London");
+
+        // A variation that can't be played again keeps the whole old line
+        let mut pos = Position::default();
+        play(&mut pos, &["d4", "d5", "c4"]);
+        pos.unmake_move();
+        play(&mut pos, &["Nf3"]);
+        pos.go_to(GameTree::ROOT);
+        pos.insert_move(Move::parse(&pos, "Nf3").unwrap());
+        pos.insert_move(Move::parse(&pos, "Nf6").unwrap());
+        let tree = pos.tree();
+        assert_eq!(
+            sans(&pos, &tree.mainline()),
+            ["Nf3", "Nf6", "d4", "d5", "c4"]
+        );
+        let old = tree.children(GameTree::ROOT)[1];
+        assert_eq!(tree.san(old), Some("d4"));
+        let d5 = tree.next(old).unwrap();
+        assert_eq!(sans(&pos, tree.children(d5)), ["c4", "Nf3"]);
+    }
+}
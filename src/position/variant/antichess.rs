@@ -0,0 +1,112 @@
+//! Antichess: captures are forced and the first side to lose all its pieces wins
+
+use super::Rules;
+use crate::position::{
+    board::Board,
+    moves::{Move, MoveKind},
+    status::GameStatus,
+    Color, PieceType,
+};
+
+#[derive(Debug)]
+pub struct Antichess;
+
+impl Rules for Antichess {
+    /// The king can be captured like any other piece, so there is no check.
+    /// Pawns may promote to a king too
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        let mut moves = board.pseudo_legal_moves();
+        moves.retain(|x| x.kind != MoveKind::Castle);
+        let kings = moves
+            .iter()
+            .filter(|x| x.promotion == Some(PieceType::Queen))
+            .map(|x| x.with_promotion(PieceType::King))
+            .collect::<Vec<_>>();
+        moves.extend(kings);
+        let enemy = board.color(board.turn.opposite());
+        if moves
+            .iter()
+            .any(|x| x.kind == MoveKind::EnPassant || enemy.contains(x.end))
+        {
+            moves.retain(|x| x.kind == MoveKind::EnPassant || enemy.contains(x.end));
+        }
+        moves
+    }
+
+    fn in_check(&self, _board: &Board, _color: Color) -> bool {
+        false
+    }
+
+    fn has_royal_king(&self, _color: Color) -> bool {
+        false
+    }
+
+    fn outcome(&self, board: &Board) -> Option<GameStatus> {
+        [board.turn, board.turn.opposite()]
+            .into_iter()
+            .find(|&color| board.color(color).is_empty())
+            .map(|winner| GameStatus::NoPiecesLeft { winner })
+    }
+
+    fn no_moves(&self, board: &Board) -> GameStatus {
+        GameStatus::NoMovesLeft { winner: board.turn }
+    }
+
+    fn insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{perft::check_perft, Color, GameStatus, Move, Position, Variant};
+
+    #[test]
+    fn perft() {
+        check_perft(
+            Variant::Antichess,
+            Variant::Antichess.starting_fen(),
+            &[20, 400, 8067, 153_299],
+        );
+    }
+
+    #[test]
+    fn forced_captures() {
+        let mut pos = Position::try_from_variant(
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 2",
+            Variant::Antichess,
+        )
+        .unwrap();
+        assert_eq!(pos.get_moves().len(), 1);
+        assert!(Move::parse(&pos, "e5").is_err());
+        pos.make_move(Move::parse(&pos, "exd5").unwrap());
+        assert_eq!(
+            Move::parse(&pos, "Qxd5").map(|x| x.to_string()),
+            Ok("d8d5".to_string())
+        );
+    }
+
+    #[test]
+    fn end_of_game() {
+        let pos =
+            Position::try_from_variant("8/8/8/8/8/8/8/6k1 w - - 0 1", Variant::Antichess).unwrap();
+        assert_eq!(
+            pos.status(),
+            GameStatus::NoPiecesLeft {
+                winner: Color::White
+            }
+        );
+        // The white pawn is blocked, so white wins by having no moves
+        let pos =
+            Position::try_from_variant("8/8/8/8/8/p7/P7/8 w - - 0 1", Variant::Antichess).unwrap();
+        assert_eq!(
+            pos.status(),
+            GameStatus::NoMovesLeft {
+                winner: Color::White
+            }
+        );
+        let promotion =
+            Position::try_from_variant("8/P7/8/8/8/8/8/7k w - - 0 1", Variant::Antichess).unwrap();
+        assert!(Move::parse(&promotion, "a8=K").is_ok());
+    }
+}
@@ -0,0 +1,132 @@
+//! Atomic: every capture explodes the pieces around it
+
+use super::Rules;
+use crate::position::{
+    bitboard::king_attacks,
+    board::Board,
+    moves::{Move, MoveKind},
+    status::GameStatus,
+    Color, PieceType,
+};
+
+#[derive(Debug)]
+pub struct Atomic;
+
+impl Rules for Atomic {
+    /// Kings can't capture, and blowing up the enemy king is legal even when in check
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        let turn = board.turn;
+        let mut moves = board.pseudo_legal_moves();
+        moves.retain(|&r#move| {
+            let is_king = board.king(turn) == Some(r#move.start);
+            if is_king && r#move.kind == MoveKind::Normal && board.get(r#move.end).is_some() {
+                return false;
+            }
+            let mut next = *board;
+            self.play(&mut next, r#move);
+            next.king(turn).is_some()
+                && (next.king(turn.opposite()).is_none() || !self.in_check(&next, turn))
+        });
+        moves
+    }
+
+    fn play(&self, board: &mut Board, r#move: Move) {
+        let capture = match r#move.kind {
+            MoveKind::Normal => board.get(r#move.end).is_some(),
+            MoveKind::EnPassant => true,
+            MoveKind::Castle | MoveKind::Drop(_) => false,
+        };
+        board.standard_play(r#move);
+        if !capture {
+            return;
+        }
+        // The capturing piece explodes too, but pawns next to it survive
+        board.change_state(|board| {
+            board.set(r#move.end, None);
+            for square in king_attacks(r#move.end).squares() {
+                let Some(piece) = board.get(square) else {
+                    continue;
+                };
+                if piece.piece_type == PieceType::Pawn {
+                    continue;
+                }
+                board.set(square, None);
+                board.castling_rights.update(square);
+                if piece.piece_type == PieceType::King {
+                    board.castling_rights.remove(piece.color);
+                }
+            }
+        });
+    }
+
+    /// Kings next to each other can't be in check, since capturing one would blow up both
+    fn in_check(&self, board: &Board, color: Color) -> bool {
+        let (Some(king), Some(enemy)) = (board.king(color), board.king(color.opposite())) else {
+            return false;
+        };
+        !king_attacks(king).contains(enemy) && board.king_attacked(color)
+    }
+
+    fn outcome(&self, board: &Board) -> Option<GameStatus> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|&color| board.king(color.opposite()).is_none())
+            .map(|winner| GameStatus::KingExploded { winner })
+    }
+
+    /// Any piece that can capture next to the enemy king can still win
+    fn insufficient_material(&self, board: &Board) -> bool {
+        board.occupied() == board.pieces(PieceType::King)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{perft::check_perft, Color, GameStatus, Move, Position, Square, Variant};
+
+    #[test]
+    fn perft() {
+        check_perft(
+            Variant::Atomic,
+            Variant::Atomic.starting_fen(),
+            &[20, 400, 8902, 197_326],
+        );
+    }
+
+    #[test]
+    fn explosions() {
+        // Nxd7 blows up the knight, the pieces around d7 and with them the black king
+        let mut pos = Position::try_from_variant(
+            "rnbqkbnr/pppppppp/8/2N5/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1",
+            Variant::Atomic,
+        )
+        .unwrap();
+        pos.make_move(Move::parse(&pos, "Nxd7").unwrap());
+        assert_eq!(
+            pos.to_fen(),
+            "rn3bnr/ppp1pppp/8/8/8/8/PPPPPPPP/R1BQKBNR b KQ - 0 1"
+        );
+        assert_eq!(
+            pos.status(),
+            GameStatus::KingExploded {
+                winner: Color::White
+            }
+        );
+        assert_eq!(pos.board.key(), pos.board.compute_key());
+        assert_eq!(
+            pos.board.get(Square::new(6, 2)).map(|x| x.color),
+            Some(Color::Black)
+        );
+    }
+
+    #[test]
+    fn kings_cannot_capture() {
+        let pos =
+            Position::try_from_variant("4k3/8/8/8/8/8/4p3/4K3 w - - 0 1", Variant::Atomic).unwrap();
+        assert!(Move::parse(&pos, "Kxe2").is_err());
+        // Next to each other the kings can't check
+        let pos =
+            Position::try_from_variant("8/8/8/8/8/8/3kr3/4K3 w - - 0 1", Variant::Atomic).unwrap();
+        assert!(!pos.board.in_check(Color::White));
+    }
+}
@@ -0,0 +1,166 @@
+//! Crazyhouse: captured pieces go into the pocket of the capturing side and can be dropped
+
+use super::Rules;
+use crate::position::{
+    bitboard::Bitboard,
+    board::{Board, POCKET_PIECES},
+    moves::{Move, MoveKind},
+    Piece, PieceType,
+};
+
+#[derive(Debug)]
+pub struct Crazyhouse;
+
+impl Rules for Crazyhouse {
+    /// Pieces can be dropped on any empty square, except pawns on the first and last rank
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        let mut moves = board.standard_legal_moves();
+        let turn = board.turn;
+        let empty = !board.occupied();
+        let back_ranks = Bitboard(0xFF00_0000_0000_00FF);
+        let in_check = board.in_check(turn);
+        for piece_type in POCKET_PIECES {
+            if board.pocket(turn, piece_type) == 0 {
+                continue;
+            }
+            let targets = if piece_type == PieceType::Pawn {
+                empty & !back_ranks
+            } else {
+                empty
+            };
+            moves.extend(
+                targets
+                    .squares()
+                    .map(|square| Move::drop_piece(piece_type, square))
+                    // A drop can only matter for the own king by blocking a check
+                    .filter(|&r#move| {
+                        if !in_check {
+                            return true;
+                        }
+                        let mut next = *board;
+                        self.play(&mut next, r#move);
+                        !next.in_check(turn)
+                    }),
+            );
+        }
+        moves
+    }
+
+    fn play(&self, board: &mut Board, r#move: Move) {
+        let turn = board.turn;
+        if let MoveKind::Drop(piece_type) = r#move.kind {
+            board.set_pocket(
+                turn,
+                piece_type,
+                board.pocket(turn, piece_type).saturating_sub(1),
+            );
+            board.set(r#move.end, Some(Piece::new(piece_type, turn)));
+            board.change_state(|board| {
+                board.en_passant = None;
+                board.turn = turn.opposite();
+            });
+            return;
+        }
+        let captured = match r#move.kind {
+            MoveKind::EnPassant => Some(PieceType::Pawn),
+            MoveKind::Normal => board.get(r#move.end).map(|piece| {
+                if board.promoted().contains(r#move.end) {
+                    PieceType::Pawn
+                } else {
+                    piece.piece_type
+                }
+            }),
+            MoveKind::Castle | MoveKind::Drop(_) => None,
+        };
+        if let Some(piece_type) = captured {
+            board.set_pocket(turn, piece_type, board.pocket(turn, piece_type) + 1);
+        }
+        let was_promoted = board.promoted().contains(r#move.start);
+        let start = Bitboard::from_square(r#move.start);
+        let end = Bitboard::from_square(r#move.end);
+        let mut promoted = board.promoted() & !start & !end;
+        if was_promoted || r#move.promotion.is_some() {
+            promoted |= end;
+        }
+        board.set_promoted(promoted);
+        board.standard_play(r#move);
+    }
+
+    /// Captured pieces come back, so there is always enough material
+    fn insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{
+        perft::check_perft, testing::play, Color, GameTree, Move, PieceType, Position, Square,
+        Variant,
+    };
+
+    #[test]
+    fn perft() {
+        check_perft(
+            Variant::Crazyhouse,
+            Variant::Crazyhouse.starting_fen(),
+            &[20, 400, 8902, 197_281],
+        );
+    }
+
+    #[test]
+    fn captures_and_drops() {
+        let mut pos = Position::new(Variant::Crazyhouse);
+        play(&mut pos, &["e4", "d5", "exd5", "Qxd5", "Nc3"]);
+        assert_eq!(pos.board.pocket(Color::White, PieceType::Pawn), 1);
+        assert_eq!(pos.board.pocket(Color::Black, PieceType::Pawn), 1);
+        assert_eq!(
+            pos.to_fen(),
+            "rnb1kbnr/ppp1pppp/8/3q4/8/2N5/PPPP1PPP/R1BQKBNR[Pp] b KQkq - 1 3"
+        );
+        let drop = Move::parse(&pos, "P@e4").unwrap();
+        assert_eq!(drop.to_string(), "P@e4");
+        assert_eq!(drop.get_notation(&pos).as_deref(), Some("P@e4"));
+        assert_eq!(Move::parse(&pos, "@e4"), Ok(drop));
+        assert!(Move::parse(&pos, "P@e1").is_err());
+        assert!(Move::parse(&pos, "N@e5").is_err());
+        pos.make_move(drop);
+        assert_eq!(pos.board.pocket(Color::Black, PieceType::Pawn), 0);
+        assert_eq!(pos.board.key(), pos.board.compute_key());
+        assert_eq!(
+            Position::try_from_variant(pos.to_fen(), Variant::Crazyhouse)
+                .unwrap()
+                .to_fen(),
+            pos.to_fen()
+        );
+    }
+
+    #[test]
+    fn drops_without_the_piece() {
+        // Drops that aren't in the pocket are ignored like moves from an empty square
+        let mut pos = Position::new(Variant::Crazyhouse);
+        let fen = pos.to_fen();
+        pos.make_move(Move::drop_piece(PieceType::Queen, Square::new(3, 3)));
+        assert_eq!(pos.to_fen(), fen);
+        assert!(pos.tree().children(GameTree::ROOT).is_empty());
+        assert!(!pos.can_undo());
+        let mut pos = Position::default();
+        pos.make_move(Move::drop_piece(PieceType::Knight, Square::new(3, 3)));
+        assert_eq!(pos.to_fen(), fen.replace("[]", ""));
+    }
+
+    #[test]
+    fn promoted_pieces_return_as_pawns() {
+        let mut pos =
+            Position::try_from_variant("3rk3/2P5/8/8/8/8/8/4K3[] w - - 0 1", Variant::Crazyhouse)
+                .unwrap();
+        pos.make_move(Move::parse(&pos, "cxd8=Q+").unwrap());
+        assert_eq!(pos.to_fen(), "3Q~k3/8/8/8/8/8/8/4K3[R] b - - 0 1");
+        pos.make_move(Move::parse(&pos, "Kxd8").unwrap());
+        assert_eq!(pos.to_fen(), "3k4/8/8/8/8/8/8/4K3[Rp] w - - 0 2");
+        let fen = "3Q~k3/8/8/8/8/8/8/4K3[R] b - - 0 1";
+        let pos = Position::try_from_variant(fen, Variant::Crazyhouse).unwrap();
+        assert_eq!(pos.to_fen(), fen);
+        assert!(Move::parse(&pos, "Kxd8").is_ok());
+    }
+}
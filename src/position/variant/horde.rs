@@ -0,0 +1,80 @@
+//! Horde: white has 36 pawns and no king against a normal black army
+
+use super::Rules;
+use crate::position::{
+    board::Board,
+    moves::{Move, Square},
+    status::GameStatus,
+    Color, PieceType,
+};
+
+#[derive(Debug)]
+pub struct Horde;
+
+impl Rules for Horde {
+    /// Pawns on the first rank may move two squares as well, but can't be taken en passant
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        let mut moves = board.standard_legal_moves();
+        if board.turn == Color::White {
+            let empty = !board.occupied();
+            for pawn in board.colored(PieceType::Pawn, Color::White).squares() {
+                let (one, two) = (Square::new(1, pawn.col), Square::new(2, pawn.col));
+                if pawn.row == 0 && empty.contains(one) && empty.contains(two) {
+                    moves.push(Move::new(pawn, two));
+                }
+            }
+        }
+        moves
+    }
+
+    fn play(&self, board: &mut Board, r#move: Move) {
+        let from_first_rank = r#move.start.row == 0
+            && board
+                .get(r#move.start)
+                .is_some_and(|x| x.piece_type == PieceType::Pawn);
+        board.standard_play(r#move);
+        if from_first_rank {
+            board.change_state(|board| board.en_passant = None);
+        }
+    }
+
+    fn has_royal_king(&self, color: Color) -> bool {
+        color == Color::Black
+    }
+
+    fn outcome(&self, board: &Board) -> Option<GameStatus> {
+        board
+            .color(Color::White)
+            .is_empty()
+            .then_some(GameStatus::HordeDestroyed)
+    }
+
+    fn insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{perft::check_perft, GameStatus, Move, Position, Variant};
+
+    #[test]
+    fn perft() {
+        check_perft(
+            Variant::Horde,
+            Variant::Horde.starting_fen(),
+            &[8, 128, 1274, 23310],
+        );
+    }
+
+    #[test]
+    fn first_rank_pawns() {
+        let mut pos =
+            Position::try_from_variant("4k3/8/8/8/8/8/8/P7 w - - 0 1", Variant::Horde).unwrap();
+        pos.make_move(Move::parse(&pos, "a3").unwrap());
+        assert_eq!(pos.to_fen(), "4k3/8/8/8/8/P7/8/8 b - - 0 1");
+        let pos =
+            Position::try_from_variant("4k3/8/8/8/8/8/8/8 w - - 0 1", Variant::Horde).unwrap();
+        assert_eq!(pos.status(), GameStatus::HordeDestroyed);
+    }
+}
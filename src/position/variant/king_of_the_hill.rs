@@ -0,0 +1,44 @@
+//! King of the Hill: bringing the king to the center wins
+
+use super::Rules;
+use crate::position::{bitboard::Bitboard, board::Board, status::GameStatus, Color, PieceType};
+
+/// d4, e4, d5 and e5
+const CENTER: Bitboard = Bitboard(0x0000_0018_1800_0000);
+
+#[derive(Debug)]
+pub struct KingOfTheHill;
+
+impl Rules for KingOfTheHill {
+    fn outcome(&self, board: &Board) -> Option<GameStatus> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|&color| !(board.colored(PieceType::King, color) & CENTER).is_empty())
+            .map(|winner| GameStatus::KingInTheCenter { winner })
+    }
+
+    /// A king can always walk to the center
+    fn insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{Color, GameStatus, Move, Position, Variant};
+
+    #[test]
+    fn king_in_the_center() {
+        let mut pos =
+            Position::try_from_variant("4k3/8/8/8/8/4K3/8/8 w - - 0 1", Variant::KingOfTheHill)
+                .unwrap();
+        assert_eq!(pos.status(), GameStatus::Ongoing);
+        pos.make_move(Move::parse(&pos, "Kd4").unwrap());
+        assert_eq!(
+            pos.status(),
+            GameStatus::KingInTheCenter {
+                winner: Color::White
+            }
+        );
+    }
+}
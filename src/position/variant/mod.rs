@@ -0,0 +1,169 @@
+//! Chess variants and the rules that set them apart from standard chess
+
+use std::fmt;
+
+use super::{board::Board, moves::Move, status::GameStatus, Color};
+
+mod antichess;
+mod atomic;
+mod crazyhouse;
+mod horde;
+mod king_of_the_hill;
+mod three_check;
+
+/// The set of rules a game is played by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Variant {
+    /// Standard chess
+    #[default]
+    Standard,
+    /// Bringing the king to one of the four center squares also wins
+    KingOfTheHill,
+    /// Checking the opponent three times also wins
+    ThreeCheck,
+    /// Captures are forced and losing all pieces wins. The king is a normal piece
+    Antichess,
+    /// Captures explode every piece but pawns next to the captured one
+    Atomic,
+    /// White has a horde of pawns and no king, and wins by checkmate
+    Horde,
+    /// Captured pieces change sides and can be dropped back on the board
+    Crazyhouse,
+}
+
+impl Variant {
+    /// Every variant
+    pub const ALL: [Self; 7] = [
+        Self::Standard,
+        Self::KingOfTheHill,
+        Self::ThreeCheck,
+        Self::Antichess,
+        Self::Atomic,
+        Self::Horde,
+        Self::Crazyhouse,
+    ];
+
+    /// The value of the `UCI_Variant` option for the variant
+    #[must_use]
+    pub const fn uci_name(self) -> &'static str {
+        match self {
+            Self::Standard => "chess",
+            Self::KingOfTheHill => "kingofthehill",
+            Self::ThreeCheck => "3check",
+            Self::Antichess => "antichess",
+            Self::Atomic => "atomic",
+            Self::Horde => "horde",
+            Self::Crazyhouse => "crazyhouse",
+        }
+    }
+
+    /// The fen of the position the variant starts from
+    #[must_use]
+    pub const fn starting_fen(self) -> &'static str {
+        match self {
+            Self::Standard | Self::KingOfTheHill | Self::Atomic => {
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            }
+            Self::ThreeCheck => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1",
+            Self::Antichess => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            Self::Horde => {
+                "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1"
+            }
+            Self::Crazyhouse => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1",
+        }
+    }
+
+    /// The rules move generation and the end of the game go through
+    pub(super) fn rules(self) -> &'static dyn Rules {
+        match self {
+            Self::Standard => &Standard,
+            Self::KingOfTheHill => &king_of_the_hill::KingOfTheHill,
+            Self::ThreeCheck => &three_check::ThreeCheck,
+            Self::Antichess => &antichess::Antichess,
+            Self::Atomic => &atomic::Atomic,
+            Self::Horde => &horde::Horde,
+            Self::Crazyhouse => &crazyhouse::Crazyhouse,
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Standard => "Standard",
+            Self::KingOfTheHill => "King of the Hill",
+            Self::ThreeCheck => "Three-check",
+            Self::Antichess => "Antichess",
+            Self::Atomic => "Atomic",
+            Self::Horde => "Horde",
+            Self::Crazyhouse => "Crazyhouse",
+        })
+    }
+}
+
+/// What a variant changes about the rules.
+/// Every method defaults to the rules of standard chess
+pub trait Rules: fmt::Debug + Sync {
+    /// Every legal move of the side to move
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        board.standard_legal_moves()
+    }
+
+    /// Plays a move that is legal on the board
+    fn play(&self, board: &mut Board, r#move: Move) {
+        board.standard_play(r#move);
+    }
+
+    /// Whether `color` is in check
+    fn in_check(&self, board: &Board, color: Color) -> bool {
+        board.king_attacked(color)
+    }
+
+    /// Whether `color` has a king that can be checked, which the fen has to contain once
+    fn has_royal_king(&self, _color: Color) -> bool {
+        true
+    }
+
+    /// Ends the game before the side to move gets to move, e.g. because a king is gone
+    fn outcome(&self, _board: &Board) -> Option<GameStatus> {
+        None
+    }
+
+    /// How the game ends when the side to move has no legal moves
+    fn no_moves(&self, board: &Board) -> GameStatus {
+        if board.in_check(board.turn) {
+            GameStatus::Checkmate {
+                winner: board.turn.opposite(),
+            }
+        } else {
+            GameStatus::Stalemate
+        }
+    }
+
+    /// Whether neither side can win anymore
+    fn insufficient_material(&self, board: &Board) -> bool {
+        board.is_insufficient_material()
+    }
+}
+
+/// The rules of standard chess
+#[derive(Debug)]
+struct Standard;
+
+impl Rules for Standard {}
+
+#[cfg(test)]
+mod test {
+    use super::Variant;
+    use crate::position::Position;
+
+    #[test]
+    fn starting_positions() {
+        for variant in Variant::ALL {
+            let pos = Position::new(variant);
+            assert_eq!(pos.variant(), variant);
+            assert_eq!(pos.to_fen(), variant.starting_fen());
+            assert!(!pos.status().is_over());
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Three-check: giving check three times wins
+
+use super::Rules;
+use crate::position::{board::Board, moves::Move, status::GameStatus, Color, PieceType};
+
+#[derive(Debug)]
+pub struct ThreeCheck;
+
+impl Rules for ThreeCheck {
+    fn play(&self, board: &mut Board, r#move: Move) {
+        let color = board.turn;
+        board.standard_play(r#move);
+        if board.in_check(board.turn) {
+            board.set_checks(color, board.checks(color) + 1);
+        }
+    }
+
+    fn outcome(&self, board: &Board) -> Option<GameStatus> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|&color| board.checks(color) >= 3)
+            .map(|winner| GameStatus::ThirdCheck { winner })
+    }
+
+    /// Any piece can still give checks, so only bare kings are a draw
+    fn insufficient_material(&self, board: &Board) -> bool {
+        board.occupied() == board.pieces(PieceType::King)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{Color, GameStatus, Move, Position, Variant};
+
+    #[test]
+    fn third_check() {
+        let mut pos =
+            Position::try_from_variant("4k3/8/8/8/8/8/8/3QK3 w - - 1+3 0 1", Variant::ThreeCheck)
+                .unwrap();
+        assert_eq!(pos.board.checks(Color::White), 2);
+        pos.make_move(Move::parse(&pos, "Qd8+").unwrap());
+        assert_eq!(
+            pos.status(),
+            GameStatus::ThirdCheck {
+                winner: Color::White
+            }
+        );
+        assert_eq!(pos.to_fen(), "3Qk3/8/8/8/8/8/8/4K3 b - - 0+3 1 1");
+        pos.unmake_move();
+        assert_eq!(pos.board.checks(Color::White), 2);
+    }
+}
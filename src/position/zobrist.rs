@@ -0,0 +1,170 @@
+//! Random keys for zobrist hashing.
+//! The keys are generated at compile time, so hashes stay the same between runs
+
+use super::{bitboard::Bitboard, moves::Square, Color, Piece, PieceType};
+
+/// Generates the keys with splitmix64
+const fn keys<const N: usize>(seed: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut state = seed;
+    let mut i = 0;
+    while i < N {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+/// Indexed by color, then piece type, then square
+const PIECES: [u64; 768] = keys(1);
+/// White kingside, white queenside, black kingside, black queenside
+pub const CASTLING: [u64; 4] = keys(2);
+/// Indexed by the file of the en passant square
+const EN_PASSANT: [u64; 8] = keys(3);
+/// Xored in when black is to move
+pub const BLACK_TO_MOVE: u64 = keys::<1>(4)[0];
+/// Indexed by color, then piece type without the king, then the number of pieces in the pocket
+const POCKETS: [u64; 2 * 5 * 32] = keys(5);
+/// Indexed by color, then the number of checks given
+const CHECKS: [u64; 2 * 4] = keys(6);
+/// Indexed by the square of a promoted crazyhouse piece
+const PROMOTED: [u64; 64] = keys(7);
+
+pub const fn piece(piece: Piece, square: Square) -> u64 {
+    PIECES[(piece.color as usize * 6 + piece.piece_type as usize) * 64 + square.index()]
+}
+
+pub const fn en_passant(square: Square) -> u64 {
+    EN_PASSANT[square.col as usize]
+}
+
+/// An empty pocket doesn't change the hash, so standard positions hash the same in crazyhouse
+pub const fn pocket(color: Color, piece_type: PieceType, count: u8) -> u64 {
+    if count == 0 || matches!(piece_type, PieceType::King) {
+        return 0;
+    }
+    let count = if count > 31 { 31 } else { count as usize };
+    POCKETS[(color as usize * 5 + piece_type as usize - 1) * 32 + count]
+}
+
+/// Promoted pieces go back into the pocket as pawns when captured,
+/// so they make a different position from the same piece that wasn't promoted
+pub fn promoted(promoted: Bitboard) -> u64 {
+    promoted
+        .squares()
+        .fold(0, |key, square| key ^ PROMOTED[square.index()])
+}
+
+pub const fn checks(color: Color, count: u8) -> u64 {
+    if count == 0 {
+        return 0;
+    }
+    let count = if count > 3 { 3 } else { count as usize };
+    CHECKS[color as usize * 4 + count]
+}
+
+pub const fn side(color: Color) -> u64 {
+    match color {
+        Color::White => 0,
+        Color::Black => BLACK_TO_MOVE,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{board::Board, Move, Position, Square, Variant};
+
+    fn check_incremental(board: &Board, depth: usize) {
+        assert_eq!(board.key(), board.compute_key());
+        if depth == 0 {
+            return;
+        }
+        for r#move in board.legal_moves() {
+            let mut next = *board;
+            next.play(r#move);
+            check_incremental(&next, depth - 1);
+        }
+    }
+
+    #[test]
+    fn incremental() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1",
+        ] {
+            check_incremental(&Position::try_from(fen).unwrap().board, 3);
+        }
+    }
+
+    #[test]
+    fn repetition() {
+        let mut pos = Position::default();
+        let start = pos.key();
+        let knight_moves = [
+            Move::new(Square::new(0, 6), Square::new(2, 5)),
+            Move::new(Square::new(7, 6), Square::new(5, 5)),
+            Move::new(Square::new(2, 5), Square::new(0, 6)),
+            Move::new(Square::new(5, 5), Square::new(7, 6)),
+        ];
+        for r#move in knight_moves {
+            assert_eq!(pos.repetition_count(), 1);
+            pos.make_move(r#move);
+        }
+        assert_eq!(pos.key(), start);
+        assert_eq!(pos.repetition_count(), 2);
+        for r#move in knight_moves {
+            pos.make_move(r#move);
+        }
+        assert_eq!(pos.repetition_count(), 3);
+        pos.unmake_move();
+        assert_eq!(pos.repetition_count(), 2);
+    }
+
+    #[test]
+    fn promoted_pieces() {
+        let promoted = "3Q~k3/8/8/8/8/8/8/4K3[R] b - - 0 1";
+        let queen = "3Qk3/8/8/8/8/8/8/4K3[R] b - - 0 1";
+        let key = |fen| {
+            Position::try_from_variant(fen, Variant::Crazyhouse)
+                .unwrap()
+                .key()
+        };
+        assert_ne!(key(promoted), key(queen));
+        let mut pos =
+            Position::try_from_variant("3rk3/2P5/8/8/8/8/8/4K3[] w - - 0 1", Variant::Crazyhouse)
+                .unwrap();
+        pos.make_move(Move::parse(&pos, "cxd8=Q+").unwrap());
+        assert_eq!(pos.key(), key(promoted));
+        assert_eq!(pos.board.key(), pos.board.compute_key());
+        check_incremental(
+            &Position::try_from_variant(
+                "r3k2r/1P4P1/8/8/8/8/8/R2Q~K2R[Nb] w KQkq - 0 1",
+                Variant::Crazyhouse,
+            )
+            .unwrap()
+            .board,
+            2,
+        );
+    }
+
+    #[test]
+    fn unusable_en_passant() {
+        let mut pos = Position::default();
+        pos.make_move(Move::new(Square::new(1, 4), Square::new(3, 4)));
+        let without =
+            Position::try_from("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        assert_eq!(pos.key(), without.unwrap().key());
+
+        let with = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        let without = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        assert_ne!(
+            Position::try_from(with).unwrap().key(),
+            Position::try_from(without).unwrap().key()
+        );
+    }
+}
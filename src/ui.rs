@@ -1,7 +1,11 @@
 //! This module is responsible for handling all ui operations
 //! It uses an [`App`] instance for this
 
-use ratatui::Frame;
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    text::Line,
+    Frame,
+};
 
 use crate::{app::App, position::ScreenLayout};
 
@@ -9,5 +13,28 @@ use crate::{app::App, position::ScreenLayout};
 /// It probably assumes a lot about the
 /// terminal being in raw mode etc.
 pub fn ui(frame: &mut Frame, app: &App) {
-    app.position.draw(frame, frame.size(), ScreenLayout::Small);
+    let (area, message_area) = split_message(app, frame.size());
+    if let (Some(message), Some(message_area)) = (&app.message, message_area) {
+        frame.render_widget(Line::raw(message.as_str()), message_area);
+    }
+    if let Some(indexing) = &app.indexing {
+        indexing.draw(frame, area);
+    } else if let Some(list) = app.games.as_ref().filter(|_| app.browsing) {
+        list.draw(frame, area);
+    } else if let Some(editor) = &app.editor {
+        editor.draw(frame, area);
+    } else {
+        app.position.draw(frame, area, ScreenLayout::Small);
+    }
+}
+
+/// Splits the screen into the part the app is drawn in and the line of [`App::message`],
+/// if there is one. Mouse events have to be matched against the same part
+#[must_use]
+pub fn split_message(app: &App, area: Rect) -> (Rect, Option<Rect>) {
+    if app.message.is_none() {
+        return (area, None);
+    }
+    let [rest, message] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+    (rest, Some(message))
 }